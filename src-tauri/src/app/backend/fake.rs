@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use super::{MemoryBackend, MemoryRegion, ModuleInfo, RegionType, PAGE_EXECUTE_READWRITE};
+use crate::app::constants::SKY_EXE;
+use crate::app::fixtures::TINY_EXE;
+use crate::app::pe::PeImage;
+use crate::app::profile::default_profile;
+use crate::app::state::ProcessHandle;
+
+/// Where [`FakeProcess::sky`] maps the PE fixture.
+pub(crate) const SKY_BASE: usize = 0x1_4000_0000;
+
+struct FakeRegion {
+  bytes: Vec<u8>,
+  protection: u32,
+}
+
+#[derive(Default)]
+struct FakeMemory {
+  regions: BTreeMap<usize, FakeRegion>,
+  modules: Vec<ModuleInfo>,
+  suspended: usize,
+}
+
+impl FakeMemory {
+  fn region_mut(&mut self, address: usize, size: usize) -> Option<(&mut FakeRegion, usize)> {
+    let (start, region) = self.regions.range_mut(..=address).next_back()?;
+    let offset = address - *start;
+    if offset.checked_add(size)? > region.bytes.len() {
+      return None;
+    }
+    Some((region, offset))
+  }
+}
+
+/// In-memory stand-in for a game process. Clones share the same memory, so a
+/// test can keep one copy to inspect what a command wrote through the other.
+#[derive(Clone, Default)]
+pub(crate) struct FakeProcess {
+  pid: u32,
+  memory: Arc<Mutex<FakeMemory>>,
+}
+
+impl FakeProcess {
+  pub(crate) fn new(pid: u32) -> Self {
+    Self {
+      pid,
+      memory: Arc::default(),
+    }
+  }
+
+  /// Maps `image` at `base` and registers it as a loaded module.
+  pub(crate) fn with_module(self, name: &str, base: usize, image: Vec<u8>) -> Self {
+    {
+      let mut memory = self.memory.lock().unwrap();
+      memory.modules.push(ModuleInfo {
+        name: name.to_string(),
+        path: name.to_string(),
+        base,
+        size: image.len(),
+      });
+    }
+    self.with_region(base, image)
+  }
+
+  pub(crate) fn with_region(self, address: usize, bytes: Vec<u8>) -> Self {
+    self.memory.lock().unwrap().regions.insert(
+      address,
+      FakeRegion {
+        bytes,
+        protection: PAGE_EXECUTE_READWRITE,
+      },
+    );
+    self
+  }
+
+  pub(crate) fn is_suspended(&self) -> bool {
+    self.memory.lock().unwrap().suspended > 0
+  }
+
+  /// A process with the PE fixture mapped as Sky.exe at [`SKY_BASE`].
+  pub(crate) fn sky(pid: u32) -> Self {
+    Self::new(pid).with_module(SKY_EXE, SKY_BASE, TINY_EXE.to_vec())
+  }
+
+  /// A session attached to this process as `attach` would leave it, with each
+  /// of `sites` resolved to an RVA of Sky.exe.
+  pub(crate) fn attach(&self, sites: &[(&str, usize)]) -> ProcessHandle {
+    let mut handle = ProcessHandle::new(Box::new(self.clone()), SKY_BASE, default_profile());
+    handle.image = Some(PeImage::from_remote(self, SKY_BASE).unwrap());
+    for (name, rva) in sites {
+      handle.sites.insert(name.to_string(), Ok(SKY_BASE + rva));
+    }
+    handle
+  }
+
+  pub(crate) fn bytes(&self, address: usize, size: usize) -> Vec<u8> {
+    self.read(address, size).unwrap()
+  }
+}
+
+impl MemoryBackend for FakeProcess {
+  fn pid(&self) -> u32 {
+    self.pid
+  }
+
+  fn read(&self, address: usize, size: usize) -> Result<Vec<u8>, String> {
+    let mut memory = self.memory.lock().map_err(|_| "Fake memory lock error".to_string())?;
+    let (region, offset) = memory
+      .region_mut(address, size)
+      .ok_or_else(|| "Failed to read process memory".to_string())?;
+    Ok(region.bytes[offset..offset + size].to_vec())
+  }
+
+  fn write(&self, address: usize, bytes: &[u8]) -> Result<(), String> {
+    let mut memory = self.memory.lock().map_err(|_| "Fake memory lock error".to_string())?;
+    let (region, offset) = memory
+      .region_mut(address, bytes.len())
+      .ok_or_else(|| "Failed to write process memory".to_string())?;
+    region.bytes[offset..offset + bytes.len()].copy_from_slice(bytes);
+    Ok(())
+  }
+
+  fn protect(&self, address: usize, size: usize, protection: u32) -> Result<u32, String> {
+    let mut memory = self.memory.lock().map_err(|_| "Fake memory lock error".to_string())?;
+    let (region, _) = memory
+      .region_mut(address, size)
+      .ok_or_else(|| "Failed to change memory protection".to_string())?;
+    Ok(std::mem::replace(&mut region.protection, protection))
+  }
+
+  fn module(&self, name: &str) -> Option<ModuleInfo> {
+    let memory = self.memory.lock().ok()?;
+    memory
+      .modules
+      .iter()
+      .find(|module| module.name.eq_ignore_ascii_case(name))
+      .cloned()
+  }
+
+  fn modules(&self) -> Vec<ModuleInfo> {
+    self.memory.lock().map(|memory| memory.modules.clone()).unwrap_or_default()
+  }
+
+  fn regions(&self) -> Vec<MemoryRegion> {
+    let Ok(memory) = self.memory.lock() else {
+      return Vec::new();
+    };
+    memory
+      .regions
+      .iter()
+      .map(|(base, region)| {
+        let region = MemoryRegion::from_protection(*base, region.bytes.len(), region.protection)
+          .with_module(&memory.modules);
+        MemoryRegion {
+          kind: if region.module.is_some() { RegionType::Image } else { RegionType::Private },
+          ..region
+        }
+      })
+      .collect()
+  }
+
+  fn suspend_threads(&self) -> Vec<isize> {
+    if let Ok(mut memory) = self.memory.lock() {
+      memory.suspended += 1;
+    }
+    vec![self.pid as isize]
+  }
+
+  fn resume_threads(&self, threads: Vec<isize>) {
+    if let Ok(mut memory) = self.memory.lock() {
+      memory.suspended = memory.suspended.saturating_sub(threads.len());
+    }
+  }
+}
@@ -0,0 +1,315 @@
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::fs::FileExt;
+use std::time::{Duration, Instant};
+
+use super::{GameProcess, MemoryBackend, MemoryRegion, ModuleInfo, RegionState, RegionType};
+
+/// How long `suspend_threads` waits for every thread to take the SIGSTOP.
+const STOP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Backend for games running under Wine/Proton. Reads go through
+/// `process_vm_readv`, writes through `/proc/<pid>/mem`, which the kernel lets
+/// through regardless of page protection, so code patches need no mprotect.
+pub(crate) struct LinuxBackend {
+  pid: u32,
+  mem: File,
+}
+
+impl LinuxBackend {
+  pub(crate) fn open(pid: u32) -> Result<Self, String> {
+    let mem = OpenOptions::new()
+      .read(true)
+      .write(true)
+      .open(format!("/proc/{pid}/mem"))
+      .map_err(|_| "Failed to open Sky.exe process memory".to_string())?;
+    Ok(Self { pid, mem })
+  }
+}
+
+fn file_name(path: &str) -> &str {
+  path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+fn file_name_matches(path: &str, target: &str) -> bool {
+  file_name(path).eq_ignore_ascii_case(target)
+}
+
+/// Wine rewrites `argv[0]` of a hosted process to its Windows path (for example
+/// `Z:\...\Sky.exe`), while `/proc/<pid>/exe` usually points at the preloader,
+/// so the exe link is only a fallback.
+fn process_matches(pid: u32, target: &str) -> bool {
+  if argv0(pid).is_some_and(|argv0| file_name_matches(&argv0, target)) {
+    return true;
+  }
+  fs::read_link(format!("/proc/{pid}/exe"))
+    .map(|path| file_name_matches(&path.to_string_lossy(), target))
+    .unwrap_or(false)
+}
+
+fn argv0(pid: u32) -> Option<String> {
+  let cmdline = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+  let argv0 = cmdline.split(|byte| *byte == 0).next()?;
+  (!argv0.is_empty()).then(|| String::from_utf8_lossy(argv0).into_owned())
+}
+
+pub(crate) fn find_pids_by_name(process_name: &str) -> Vec<u32> {
+  let Ok(entries) = fs::read_dir("/proc") else {
+    return Vec::new();
+  };
+  let mut pids: Vec<u32> = entries
+    .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+    .filter(|pid| process_matches(*pid, process_name))
+    .collect();
+  pids.sort_unstable();
+  pids
+}
+
+pub(crate) fn find_pid_by_name(process_name: &str) -> Option<u32> {
+  find_pids_by_name(process_name).into_iter().next()
+}
+
+/// Every running `process_name`. Wine windows are not visible from here, so
+/// there is no window title; the path is the Windows path Wine reports.
+pub(crate) fn list_processes(process_name: &str) -> Vec<GameProcess> {
+  let boot_time = fs::read_to_string("/proc/stat").ok().and_then(|stat| {
+    stat
+      .lines()
+      .find_map(|line| line.strip_prefix("btime "))
+      .and_then(|value| value.trim().parse::<u64>().ok())
+  });
+  let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+  find_pids_by_name(process_name)
+    .into_iter()
+    .map(|pid| GameProcess {
+      pid,
+      path: argv0(pid),
+      window_title: None,
+      start_time: boot_time
+        .zip(start_time(pid))
+        .filter(|_| ticks > 0)
+        .map(|(boot, started)| boot + started / ticks as u64),
+      module_base: find_module(pid, process_name).map(|module| module.base),
+    })
+    .collect()
+}
+
+pub(crate) fn terminate(pid: u32) -> Result<(), String> {
+  let result = unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+  if result != 0 {
+    return Err("Failed to close Sky.exe".to_string());
+  }
+  Ok(())
+}
+
+/// Fields of `/proc/<pid>/stat` (or a task's `stat`) after the command name,
+/// which is parenthesised and may contain spaces.
+fn stat_fields(path: &str) -> Option<String> {
+  let stat = fs::read_to_string(path).ok()?;
+  Some(stat[stat.rfind(')')? + 2..].to_string())
+}
+
+/// Whether every thread of `pid` is stopped (`T`), or already gone. SIGSTOP
+/// is delivered asynchronously, so threads keep running for a moment after
+/// `kill` returns.
+fn all_threads_stopped(pid: u32) -> bool {
+  let Ok(tasks) = fs::read_dir(format!("/proc/{pid}/task")) else {
+    return false;
+  };
+  tasks.filter_map(Result::ok).all(|task| {
+    let path = task.path().join("stat");
+    stat_fields(&path.to_string_lossy())
+      .and_then(|fields| fields.chars().next())
+      .map_or(true, |state| matches!(state, 'T' | 't' | 'Z' | 'X'))
+  })
+}
+
+/// Start time of `pid` in clock ticks since boot (field 22 of `stat`).
+fn start_time(pid: u32) -> Option<u64> {
+  let fields = stat_fields(&format!("/proc/{pid}/stat"))?;
+  fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// Blocks until `pid` is gone. Sky is not our child, so its exit status is
+/// not available. The start time guards against the pid being reused.
+pub(crate) fn wait_for_exit(pid: u32) -> Option<i32> {
+  let started = start_time(pid);
+  while started.is_some() && start_time(pid) == started {
+    std::thread::sleep(Duration::from_millis(500));
+  }
+  None
+}
+
+/// Merges the mappings of each file accepted by `keep` into one module range
+/// per file, in order of first mapping.
+fn merged_modules(pid: u32, keep: impl Fn(&str) -> bool) -> Vec<ModuleInfo> {
+  let Ok(maps) = fs::read_to_string(format!("/proc/{pid}/maps")) else {
+    return Vec::new();
+  };
+  let mut modules: Vec<ModuleInfo> = Vec::new();
+  for mapping in maps.lines().filter_map(MapsEntry::parse) {
+    if !keep(mapping.path) {
+      continue;
+    }
+    match modules.iter_mut().find(|module| module.path == mapping.path) {
+      Some(module) => {
+        let module_end = (module.base + module.size).max(mapping.end);
+        module.base = module.base.min(mapping.start);
+        module.size = module_end - module.base;
+      }
+      None => modules.push(ModuleInfo {
+        name: file_name(mapping.path).to_string(),
+        path: mapping.path.to_string(),
+        base: mapping.start,
+        size: mapping.end - mapping.start,
+      }),
+    }
+  }
+  modules
+}
+
+fn find_module(pid: u32, name: &str) -> Option<ModuleInfo> {
+  merged_modules(pid, |path| file_name_matches(path, name)).into_iter().next()
+}
+
+/// Wine maps PE images straight from their files, as the loader does for
+/// shared objects.
+fn is_image(path: &str) -> bool {
+  let lower = file_name(path).to_ascii_lowercase();
+  path.starts_with('/')
+    && (lower.ends_with(".exe")
+      || lower.ends_with(".dll")
+      || lower.ends_with(".so")
+      || lower.contains(".so."))
+}
+
+/// One line of `/proc/<pid>/maps`. The path is everything after the inode and
+/// may contain spaces (Steam library folders usually do).
+struct MapsEntry<'a> {
+  start: usize,
+  end: usize,
+  /// `rwxp`-style permissions.
+  perms: &'a str,
+  path: &'a str,
+}
+
+impl<'a> MapsEntry<'a> {
+  fn parse(line: &'a str) -> Option<Self> {
+    let mut rest = line;
+    let mut fields = [""; 5];
+    for field in fields.iter_mut() {
+      rest = rest.trim_start();
+      let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+      *field = &rest[..end];
+      rest = &rest[end..];
+    }
+    let (start, end) = fields[0].split_once('-')?;
+    Some(Self {
+      start: usize::from_str_radix(start, 16).ok()?,
+      end: usize::from_str_radix(end, 16).ok()?,
+      perms: fields[1],
+      path: rest.trim(),
+    })
+  }
+}
+
+impl MemoryBackend for LinuxBackend {
+  fn pid(&self) -> u32 {
+    self.pid
+  }
+
+  fn read(&self, address: usize, size: usize) -> Result<Vec<u8>, String> {
+    let mut buffer = vec![0u8; size];
+    let local = libc::iovec {
+      iov_base: buffer.as_mut_ptr() as *mut _,
+      iov_len: size,
+    };
+    let remote = libc::iovec {
+      iov_base: address as *mut _,
+      iov_len: size,
+    };
+    let read = unsafe { libc::process_vm_readv(self.pid as libc::pid_t, &local, 1, &remote, 1, 0) };
+    if read < 0 || read as usize != size {
+      return Err("Failed to read process memory".to_string());
+    }
+    Ok(buffer)
+  }
+
+  fn write(&self, address: usize, bytes: &[u8]) -> Result<(), String> {
+    let written = self
+      .mem
+      .write_at(bytes, address as u64)
+      .map_err(|_| "Failed to write process memory".to_string())?;
+    if written != bytes.len() {
+      return Err("Failed to write process memory".to_string());
+    }
+    Ok(())
+  }
+
+  fn protect(&self, _address: usize, _size: usize, _protection: u32) -> Result<u32, String> {
+    Err("Changing memory protection is not supported on Linux".to_string())
+  }
+
+  fn module(&self, name: &str) -> Option<ModuleInfo> {
+    find_module(self.pid, name)
+  }
+
+  fn modules(&self) -> Vec<ModuleInfo> {
+    merged_modules(self.pid, is_image)
+  }
+
+  fn regions(&self) -> Vec<MemoryRegion> {
+    let Ok(maps) = fs::read_to_string(format!("/proc/{}/maps", self.pid)) else {
+      return Vec::new();
+    };
+    maps
+      .lines()
+      .filter_map(MapsEntry::parse)
+      .map(|mapping| {
+        let perms = mapping.perms.as_bytes();
+        // Anonymous and pseudo (`[heap]`, `[stack]`) mappings are private
+        // memory.
+        let is_file = mapping.path.starts_with('/');
+        let is_image = is_image(mapping.path);
+        MemoryRegion {
+          base: mapping.start,
+          size: mapping.end - mapping.start,
+          protection: mapping.perms.to_string(),
+          readable: perms.first() == Some(&b'r'),
+          writable: perms.get(1) == Some(&b'w'),
+          executable: perms.get(2) == Some(&b'x'),
+          state: RegionState::Commit,
+          kind: match (is_image, is_file) {
+            (true, _) => RegionType::Image,
+            (false, true) => RegionType::Mapped,
+            (false, false) => RegionType::Private,
+          },
+          module: is_image.then(|| file_name(mapping.path).to_string()),
+        }
+      })
+      .collect()
+  }
+
+  fn suspend_threads(&self) -> Vec<isize> {
+    let stopped = unsafe { libc::kill(self.pid as libc::pid_t, libc::SIGSTOP) };
+    if stopped != 0 {
+      return Vec::new();
+    }
+    let started = Instant::now();
+    while !all_threads_stopped(self.pid) {
+      if started.elapsed() > STOP_TIMEOUT {
+        log::warn!("Threads of {} did not stop within {STOP_TIMEOUT:?}", self.pid);
+        break;
+      }
+      std::thread::sleep(Duration::from_millis(1));
+    }
+    vec![self.pid as isize]
+  }
+
+  fn resume_threads(&self, threads: Vec<isize>) {
+    for pid in threads {
+      unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGCONT);
+      }
+    }
+  }
+}
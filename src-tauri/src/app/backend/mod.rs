@@ -0,0 +1,211 @@
+use serde::Serialize;
+
+use super::constants::SKY_EXE;
+#[cfg(windows)]
+use super::constants::SKY_WINDOW_CLASS;
+
+#[cfg(test)]
+pub(crate) mod fake;
+#[cfg(target_os = "linux")]
+pub(crate) mod linux;
+#[cfg(windows)]
+pub(crate) mod windows;
+
+#[cfg(any(test, windows))]
+pub(crate) const PAGE_EXECUTE_READWRITE: u32 = 0x40;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ModuleInfo {
+  pub(crate) name: String,
+  pub(crate) path: String,
+  pub(crate) base: usize,
+  pub(crate) size: usize,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RegionState {
+  Commit,
+  /// Address space set aside but not backed by memory yet.
+  #[cfg_attr(not(windows), allow(dead_code))]
+  Reserve,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RegionType {
+  /// Mapped from an executable image (exe/dll).
+  Image,
+  /// Mapped from another file or section.
+  Mapped,
+  Private,
+}
+
+/// A range of the target's address space, as reported by `VirtualQueryEx` or
+/// `/proc/<pid>/maps`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MemoryRegion {
+  pub(crate) base: usize,
+  pub(crate) size: usize,
+  /// Platform protection as reported, e.g. `PAGE_READWRITE` or `rw-p`.
+  pub(crate) protection: String,
+  pub(crate) readable: bool,
+  pub(crate) writable: bool,
+  pub(crate) executable: bool,
+  pub(crate) state: RegionState,
+  #[serde(rename = "type")]
+  pub(crate) kind: RegionType,
+  /// File name of the module the region belongs to, if any.
+  pub(crate) module: Option<String>,
+}
+
+#[cfg(any(test, windows))]
+impl MemoryRegion {
+  /// A committed private region with the access of a Win32 `PAGE_*` value.
+  /// Guard pages count as inaccessible, since touching one faults.
+  pub(crate) fn from_protection(base: usize, size: usize, protection: u32) -> Self {
+    const PAGE_NOACCESS: u32 = 0x01;
+    const PAGE_GUARD: u32 = 0x100;
+    let inaccessible = protection & (PAGE_NOACCESS | PAGE_GUARD) != 0 || protection == 0;
+    let access = protection & 0xFF;
+    Self {
+      base,
+      size,
+      protection: protection_name(protection),
+      readable: !inaccessible,
+      // PAGE_READWRITE, PAGE_WRITECOPY and their executable variants.
+      writable: !inaccessible && access & (0x04 | 0x08 | 0x40 | 0x80) != 0,
+      // PAGE_EXECUTE*
+      executable: !inaccessible && access & 0xF0 != 0,
+      state: RegionState::Commit,
+      kind: RegionType::Private,
+      module: None,
+    }
+  }
+
+  /// Fills in `module` for regions inside one of `modules`.
+  pub(crate) fn with_module(mut self, modules: &[ModuleInfo]) -> Self {
+    self.module = modules
+      .iter()
+      .find(|module| self.base >= module.base && self.base < module.base + module.size)
+      .map(|module| module.name.clone());
+    self
+  }
+}
+
+#[cfg(any(test, windows))]
+fn protection_name(protection: u32) -> String {
+  let mut name = match protection & 0xFF {
+    0x01 => "PAGE_NOACCESS",
+    0x02 => "PAGE_READONLY",
+    0x04 => "PAGE_READWRITE",
+    0x08 => "PAGE_WRITECOPY",
+    0x10 => "PAGE_EXECUTE",
+    0x20 => "PAGE_EXECUTE_READ",
+    0x40 => "PAGE_EXECUTE_READWRITE",
+    0x80 => "PAGE_EXECUTE_WRITECOPY",
+    _ => return format!("{protection:#x}"),
+  }
+  .to_string();
+  let modifiers = [(0x100, "PAGE_GUARD"), (0x200, "PAGE_NOCACHE"), (0x400, "PAGE_WRITECOMBINE")];
+  for (flag, modifier) in modifiers {
+    if protection & flag != 0 {
+      name.push('|');
+      name.push_str(modifier);
+    }
+  }
+  name
+}
+
+/// A running Sky.exe that could be attached to.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GameProcess {
+  pub(crate) pid: u32,
+  pub(crate) path: Option<String>,
+  pub(crate) window_title: Option<String>,
+  /// Unix time in seconds.
+  pub(crate) start_time: Option<u64>,
+  pub(crate) module_base: Option<usize>,
+}
+
+/// Raw access to the memory of an attached process. Every feature goes through
+/// this trait so the same patch logic runs against Win32 or the in-memory fake.
+/// Backends are shared, so long reads such as scans can run outside the
+/// sessions lock.
+pub(crate) trait MemoryBackend: Send + Sync {
+  fn pid(&self) -> u32;
+
+  fn read(&self, address: usize, size: usize) -> Result<Vec<u8>, String>;
+
+  fn write(&self, address: usize, bytes: &[u8]) -> Result<(), String>;
+
+  /// Changes the protection of a range and returns the previous protection.
+  #[cfg_attr(not(windows), allow(dead_code))]
+  fn protect(&self, address: usize, size: usize, protection: u32) -> Result<u32, String>;
+
+  fn module(&self, name: &str) -> Option<ModuleInfo>;
+
+  /// Every loaded module, without walking the whole address space.
+  fn modules(&self) -> Vec<ModuleInfo>;
+
+  /// Committed and reserved regions of the address space, in address order.
+  fn regions(&self) -> Vec<MemoryRegion>;
+
+  fn suspend_threads(&self) -> Vec<isize>;
+
+  fn resume_threads(&self, threads: Vec<isize>);
+}
+
+#[cfg(windows)]
+pub(crate) fn find_game_pid() -> Option<u32> {
+  windows::find_pid_by_window_class(SKY_WINDOW_CLASS)
+    .or_else(|| windows::find_pid_by_name(SKY_EXE))
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn find_game_pid() -> Option<u32> {
+  linux::find_pid_by_name(SKY_EXE)
+}
+
+#[cfg(windows)]
+pub(crate) fn list_game_processes() -> Vec<GameProcess> {
+  windows::list_processes(SKY_EXE, SKY_WINDOW_CLASS)
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn list_game_processes() -> Vec<GameProcess> {
+  linux::list_processes(SKY_EXE)
+}
+
+#[cfg(windows)]
+pub(crate) fn open(pid: u32) -> Result<Box<dyn MemoryBackend>, String> {
+  Ok(Box::new(windows::WindowsBackend::open(pid)?))
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn open(pid: u32) -> Result<Box<dyn MemoryBackend>, String> {
+  Ok(Box::new(linux::LinuxBackend::open(pid)?))
+}
+
+#[cfg(windows)]
+pub(crate) fn terminate(pid: u32) -> Result<(), String> {
+  windows::terminate(pid)
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn terminate(pid: u32) -> Result<(), String> {
+  linux::terminate(pid)
+}
+
+#[cfg(windows)]
+pub(crate) fn wait_for_exit(pid: u32) -> Option<i32> {
+  windows::wait_for_exit(pid)
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn wait_for_exit(pid: u32) -> Option<i32> {
+  linux::wait_for_exit(pid)
+}
@@ -0,0 +1,445 @@
+use std::thread;
+use std::time::Duration;
+
+use windows_sys::Win32::Foundation::{
+  CloseHandle, GetLastError, BOOL, ERROR_INVALID_PARAMETER, FILETIME, HWND, STILL_ACTIVE,
+};
+use windows_sys::Win32::System::Diagnostics::Debug::{
+  FlushInstructionCache, ReadProcessMemory, WriteProcessMemory,
+};
+use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+  CreateToolhelp32Snapshot, Module32FirstW, Module32NextW, Process32FirstW, Process32NextW,
+  Thread32First, Thread32Next, MODULEENTRY32W, PROCESSENTRY32W, THREADENTRY32, TH32CS_SNAPMODULE,
+  TH32CS_SNAPMODULE32, TH32CS_SNAPPROCESS, TH32CS_SNAPTHREAD,
+};
+use windows_sys::Win32::System::Memory::{
+  VirtualProtectEx, VirtualQueryEx, MEMORY_BASIC_INFORMATION, MEM_COMMIT, MEM_IMAGE, MEM_MAPPED,
+  MEM_RESERVE,
+};
+use windows_sys::Win32::System::Threading::{
+  GetExitCodeProcess, GetProcessTimes, OpenProcess, OpenThread, QueryFullProcessImageNameW,
+  ResumeThread, SuspendThread, TerminateProcess, WaitForSingleObject, INFINITE,
+  PROCESS_CREATE_THREAD, PROCESS_NAME_WIN32, PROCESS_QUERY_INFORMATION,
+  PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SYNCHRONIZE, PROCESS_TERMINATE, PROCESS_VM_OPERATION,
+  PROCESS_VM_READ, PROCESS_VM_WRITE, THREAD_SUSPEND_RESUME,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+  EnumWindows, GetClassNameW, GetWindowTextW, GetWindowThreadProcessId,
+};
+
+use super::{
+  GameProcess, MemoryBackend, MemoryRegion, ModuleInfo, RegionState, RegionType,
+  PAGE_EXECUTE_READWRITE,
+};
+
+/// How often [`poll_for_exit`] checks on a process it cannot wait on.
+const EXIT_POLL: Duration = Duration::from_millis(500);
+
+pub(crate) fn read_wide(buf: &[u16]) -> String {
+  let len = buf.iter().position(|c| *c == 0).unwrap_or(buf.len());
+  String::from_utf16_lossy(&buf[..len])
+}
+
+/// Every top-level window of `class_name`, as `(pid, title)`.
+fn windows_by_class(class_name: &str) -> Vec<(u32, String)> {
+  struct Search {
+    class_name: String,
+    found: Vec<(u32, String)>,
+  }
+
+  unsafe extern "system" fn enum_proc(hwnd: HWND, lparam: isize) -> BOOL {
+    let search = &mut *(lparam as *mut Search);
+    let mut buffer = [0u16; 256];
+    let len = GetClassNameW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
+    if len > 0 && read_wide(&buffer) == search.class_name {
+      let mut pid = 0u32;
+      GetWindowThreadProcessId(hwnd, &mut pid);
+      if pid != 0 {
+        let mut title = [0u16; 256];
+        GetWindowTextW(hwnd, title.as_mut_ptr(), title.len() as i32);
+        search.found.push((pid, read_wide(&title)));
+      }
+    }
+    1
+  }
+
+  let mut search = Box::new(Search {
+    class_name: class_name.to_string(),
+    found: Vec::new(),
+  });
+  let ptr = &mut *search as *mut Search as isize;
+  unsafe {
+    EnumWindows(Some(enum_proc), ptr);
+  }
+  search.found
+}
+
+pub(crate) fn find_pid_by_window_class(class_name: &str) -> Option<u32> {
+  windows_by_class(class_name).first().map(|(pid, _)| *pid)
+}
+
+pub(crate) fn find_pids_by_name(process_name: &str) -> Vec<u32> {
+  let mut pids = Vec::new();
+  unsafe {
+    let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+    if snapshot == 0 || snapshot == -1 {
+      return pids;
+    }
+    let mut entry = PROCESSENTRY32W {
+      dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+      ..std::mem::zeroed()
+    };
+    if Process32FirstW(snapshot, &mut entry) == 0 {
+      CloseHandle(snapshot);
+      return pids;
+    }
+    let target = process_name.to_ascii_lowercase();
+    loop {
+      let name = read_wide(&entry.szExeFile).to_ascii_lowercase();
+      if name == target {
+        pids.push(entry.th32ProcessID);
+      }
+      if Process32NextW(snapshot, &mut entry) == 0 {
+        break;
+      }
+    }
+    CloseHandle(snapshot);
+  }
+  pids
+}
+
+pub(crate) fn find_pid_by_name(process_name: &str) -> Option<u32> {
+  find_pids_by_name(process_name).into_iter().next()
+}
+
+/// Image path and creation time (Unix seconds) of `pid`.
+fn process_details(pid: u32) -> (Option<String>, Option<u64>) {
+  let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+  if handle == 0 {
+    return (None, None);
+  }
+  let mut buffer = [0u16; 1024];
+  let mut len = buffer.len() as u32;
+  let path = unsafe {
+    QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, buffer.as_mut_ptr(), &mut len)
+  };
+  let path = (path != 0).then(|| String::from_utf16_lossy(&buffer[..len as usize]));
+
+  let (mut created, mut exited, mut kernel, mut user) = unsafe { std::mem::zeroed() };
+  let ok = unsafe { GetProcessTimes(handle, &mut created, &mut exited, &mut kernel, &mut user) };
+  unsafe {
+    CloseHandle(handle);
+  }
+  let created: FILETIME = created;
+  let ticks = (created.dwHighDateTime as u64) << 32 | created.dwLowDateTime as u64;
+  // FILETIME counts 100ns intervals since 1601-01-01.
+  let start_time = (ok != 0)
+    .then(|| ticks.checked_sub(116_444_736_000_000_000))
+    .flatten()
+    .map(|since_epoch| since_epoch / 10_000_000);
+  (path, start_time)
+}
+
+/// Every running `process_name`, with the title of its `class_name` window
+/// if it has one yet.
+pub(crate) fn list_processes(process_name: &str, class_name: &str) -> Vec<GameProcess> {
+  let windows = windows_by_class(class_name);
+  find_pids_by_name(process_name)
+    .into_iter()
+    .map(|pid| {
+      let (path, start_time) = process_details(pid);
+      GameProcess {
+        pid,
+        path,
+        window_title: windows
+          .iter()
+          .find(|(window_pid, _)| *window_pid == pid)
+          .map(|(_, title)| title.clone()),
+        start_time,
+        module_base: find_module(pid, process_name).map(|module| module.base),
+      }
+    })
+    .collect()
+}
+
+/// Every module loaded in `pid`.
+fn modules(pid: u32) -> Vec<ModuleInfo> {
+  let mut modules = Vec::new();
+  unsafe {
+    let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32, pid);
+    if snapshot == 0 || snapshot == -1 {
+      return modules;
+    }
+    let mut entry = MODULEENTRY32W {
+      dwSize: std::mem::size_of::<MODULEENTRY32W>() as u32,
+      ..std::mem::zeroed()
+    };
+    if Module32FirstW(snapshot, &mut entry) == 0 {
+      CloseHandle(snapshot);
+      return modules;
+    }
+    loop {
+      modules.push(ModuleInfo {
+        name: read_wide(&entry.szModule),
+        path: read_wide(&entry.szExePath),
+        base: entry.modBaseAddr as usize,
+        size: entry.modBaseSize as usize,
+      });
+      if Module32NextW(snapshot, &mut entry) == 0 {
+        break;
+      }
+    }
+    CloseHandle(snapshot);
+  }
+  modules
+}
+
+fn find_module(pid: u32, name: &str) -> Option<ModuleInfo> {
+  modules(pid).into_iter().find(|module| module.name.eq_ignore_ascii_case(name))
+}
+
+pub(crate) fn terminate(pid: u32) -> Result<(), String> {
+  let handle = unsafe { OpenProcess(PROCESS_TERMINATE, 0, pid) };
+  if handle == 0 {
+    return Err("Failed to open Sky.exe process".to_string());
+  }
+  let result = unsafe { TerminateProcess(handle, 0) };
+  unsafe {
+    CloseHandle(handle);
+  }
+  if result == 0 {
+    return Err("Failed to close Sky.exe".to_string());
+  }
+  Ok(())
+}
+
+/// Blocks until `pid` exits and returns its exit code. If the process cannot
+/// be opened for waiting, e.g. because access is denied, it is polled instead.
+pub(crate) fn wait_for_exit(pid: u32) -> Option<i32> {
+  let access = PROCESS_SYNCHRONIZE | PROCESS_QUERY_LIMITED_INFORMATION;
+  let handle = unsafe { OpenProcess(access, 0, pid) };
+  if handle == 0 {
+    return poll_for_exit(pid);
+  }
+  let mut code = 0u32;
+  let ok = unsafe {
+    WaitForSingleObject(handle, INFINITE);
+    GetExitCodeProcess(handle, &mut code)
+  };
+  unsafe {
+    CloseHandle(handle);
+  }
+  if ok == 0 { None } else { Some(code as i32) }
+}
+
+/// Polls `pid` until its exit code is no longer `STILL_ACTIVE`. Only
+/// `ERROR_INVALID_PARAMETER` from `OpenProcess` means there is no such
+/// process; any other failure, such as access denied, keeps polling.
+fn poll_for_exit(pid: u32) -> Option<i32> {
+  loop {
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if handle == 0 {
+      if unsafe { GetLastError() } == ERROR_INVALID_PARAMETER {
+        return None;
+      }
+    } else {
+      let mut code = 0u32;
+      let ok = unsafe { GetExitCodeProcess(handle, &mut code) };
+      unsafe {
+        CloseHandle(handle);
+      }
+      if ok != 0 && code != STILL_ACTIVE as u32 {
+        return Some(code as i32);
+      }
+    }
+    thread::sleep(EXIT_POLL);
+  }
+}
+
+pub(crate) struct WindowsBackend {
+  handle: isize,
+  pid: u32,
+}
+
+impl WindowsBackend {
+  pub(crate) fn open(pid: u32) -> Result<Self, String> {
+    let handle = unsafe {
+      OpenProcess(
+        PROCESS_QUERY_INFORMATION
+          | PROCESS_CREATE_THREAD
+          | PROCESS_VM_READ
+          | PROCESS_VM_WRITE
+          | PROCESS_VM_OPERATION,
+        0,
+        pid,
+      )
+    };
+    if handle == 0 {
+      return Err("Failed to open Sky.exe process".to_string());
+    }
+    Ok(Self { handle, pid })
+  }
+}
+
+impl Drop for WindowsBackend {
+  fn drop(&mut self) {
+    unsafe {
+      CloseHandle(self.handle);
+    }
+  }
+}
+
+impl MemoryBackend for WindowsBackend {
+  fn pid(&self) -> u32 {
+    self.pid
+  }
+
+  fn read(&self, address: usize, size: usize) -> Result<Vec<u8>, String> {
+    let mut buffer = vec![0u8; size];
+    let mut read = 0usize;
+    let ok = unsafe {
+      ReadProcessMemory(
+        self.handle,
+        address as *const _,
+        buffer.as_mut_ptr() as *mut _,
+        size,
+        &mut read,
+      )
+    };
+    if ok == 0 || read != size {
+      return Err("Failed to read process memory".to_string());
+    }
+    Ok(buffer)
+  }
+
+  fn write(&self, address: usize, bytes: &[u8]) -> Result<(), String> {
+    let old_protect = self.protect(address, bytes.len(), PAGE_EXECUTE_READWRITE)?;
+
+    let mut written = 0usize;
+    let ok = unsafe {
+      WriteProcessMemory(
+        self.handle,
+        address as *mut _,
+        bytes.as_ptr() as *const _,
+        bytes.len(),
+        &mut written,
+      )
+    };
+
+    unsafe {
+      FlushInstructionCache(self.handle, address as *const _, bytes.len());
+    }
+    let _ = self.protect(address, bytes.len(), old_protect);
+
+    if ok == 0 || written != bytes.len() {
+      return Err("Failed to write process memory".to_string());
+    }
+    Ok(())
+  }
+
+  fn protect(&self, address: usize, size: usize, protection: u32) -> Result<u32, String> {
+    let mut old_protect = 0u32;
+    let ok = unsafe {
+      VirtualProtectEx(
+        self.handle,
+        address as *mut _,
+        size,
+        protection,
+        &mut old_protect,
+      )
+    };
+    if ok == 0 {
+      return Err("Failed to change memory protection".to_string());
+    }
+    Ok(old_protect)
+  }
+
+  fn module(&self, name: &str) -> Option<ModuleInfo> {
+    find_module(self.pid, name)
+  }
+
+  fn modules(&self) -> Vec<ModuleInfo> {
+    modules(self.pid)
+  }
+
+  fn regions(&self) -> Vec<MemoryRegion> {
+    let modules = modules(self.pid);
+    let mut regions = Vec::new();
+    let mut address = 0usize;
+    loop {
+      let mut info: MEMORY_BASIC_INFORMATION = unsafe { std::mem::zeroed() };
+      let len = unsafe {
+        VirtualQueryEx(
+          self.handle,
+          address as *const _,
+          &mut info,
+          std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+        )
+      };
+      if len == 0 {
+        break;
+      }
+      let base = info.BaseAddress as usize;
+      let state = match info.State {
+        MEM_COMMIT => Some(RegionState::Commit),
+        MEM_RESERVE => Some(RegionState::Reserve),
+        _ => None,
+      };
+      if let Some(state) = state {
+        let kind = match info.Type {
+          MEM_IMAGE => RegionType::Image,
+          MEM_MAPPED => RegionType::Mapped,
+          _ => RegionType::Private,
+        };
+        let region = MemoryRegion::from_protection(base, info.RegionSize, info.Protect);
+        regions.push(MemoryRegion { state, kind, ..region }.with_module(&modules));
+      }
+      match base.checked_add(info.RegionSize) {
+        Some(next) if next > address => address = next,
+        _ => break,
+      }
+    }
+    regions
+  }
+
+  fn suspend_threads(&self) -> Vec<isize> {
+    let mut handles = Vec::new();
+    unsafe {
+      let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+      if snapshot == 0 || snapshot == -1 {
+        return handles;
+      }
+      let mut entry = THREADENTRY32 {
+        dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+        ..std::mem::zeroed()
+      };
+      if Thread32First(snapshot, &mut entry) == 0 {
+        CloseHandle(snapshot);
+        return handles;
+      }
+      loop {
+        if entry.th32OwnerProcessID == self.pid {
+          let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+          if thread != 0 {
+            SuspendThread(thread);
+            handles.push(thread);
+          }
+        }
+        if Thread32Next(snapshot, &mut entry) == 0 {
+          break;
+        }
+      }
+      CloseHandle(snapshot);
+    }
+    handles
+  }
+
+  fn resume_threads(&self, threads: Vec<isize>) {
+    for thread in threads {
+      unsafe {
+        ResumeThread(thread);
+        CloseHandle(thread);
+      }
+    }
+  }
+}
@@ -1,140 +1,383 @@
-use tauri::State;
-use windows_sys::Win32::Foundation::CloseHandle;
-use windows_sys::Win32::System::Threading::{
-  OpenProcess, TerminateProcess, PROCESS_CREATE_THREAD, PROCESS_QUERY_INFORMATION, PROCESS_TERMINATE,
-  PROCESS_VM_OPERATION, PROCESS_VM_READ, PROCESS_VM_WRITE,
-};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use tauri::{AppHandle, Manager, State};
 
+use super::backend::{self, GameProcess, MemoryRegion};
 use super::constants::*;
-use super::process::{
-  apply_patch_at, find_pid_by_name, find_pid_by_window_class, get_module_base,
-  resume_process_threads, suspend_process_threads,
-};
+use super::features;
+use super::freeze::{self, Freeze};
+use super::pe::PeImage;
+use super::pointer::Target;
+use super::process;
+use super::profile::{self, BuildIdentity, SiteLocator};
+use super::settings;
+use super::watcher;
 use super::state::{
-  AttachResponse, ForegroundWindow, OffsetsResponse, ProcessHandle, ProcessState, StatusResponse,
+  find_session, find_session_mut, session_id, AttachResponse, DetachResponse, FeatureError,
+  FeatureInfo, ForegroundWindow, OffsetsResponse, ProcessHandle, ProcessState, SessionInfo,
+  Sessions, StatusResponse, UnresolvedSite,
 };
+use super::telemetry;
+use super::value::{Endian, Probe, Value, ValueType};
 use super::window::foreground_window_class;
 
+/// The feature catalogue, with availability for `session`. Detached, every
+/// feature is checked against the bundled default profile.
+#[tauri::command]
+pub(crate) fn list_features(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+) -> Result<Vec<FeatureInfo>, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = optional_session(&guard, session)?;
+  let fallback;
+  let active = match handle {
+    Some(handle) => &handle.profile,
+    None => {
+      fallback = profile::default_profile();
+      &fallback
+    }
+  };
+  Ok(
+    features::FEATURES
+      .iter()
+      .map(|feature| {
+        let availability = features::availability(feature, handle, active);
+        FeatureInfo {
+          feature,
+          available: availability.is_ok(),
+          unavailable_reason: availability.err(),
+        }
+      })
+      .collect(),
+  )
+}
+
+/// Toggles a registered feature by id in one session; see
+/// [`features::set_feature`]. The outcome is remembered in settings so
+/// auto-attach can re-apply it.
 #[tauri::command]
-pub(crate) fn apply_patch(
+pub(crate) fn set_feature(
+  app: AppHandle,
   state: State<'_, ProcessState>,
-  offset: u64,
-  bytes: Vec<u8>,
+  session: Option<u32>,
+  id: String,
   enabled: bool,
-) -> Result<(), String> {
-  let mut guard = state.inner.lock().map_err(|_| "State lock error".to_string())?;
-  let handle = guard.as_mut().ok_or_else(|| "Process not attached".to_string())?;
-  let address = handle.base + offset as usize;
-  let threads = suspend_process_threads(handle.pid);
-  let result = apply_patch_at(handle, address, &bytes, enabled);
-  resume_process_threads(threads);
-  result
+  params: Option<HashMap<String, f32>>,
+) -> Result<(), FeatureError> {
+  let params = params.unwrap_or_default();
+  let mut guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session_mut(&mut guard, session)?;
+  features::set_feature(handle, &id, enabled, &params)?;
+  drop(guard);
+  if let Err(error) = settings::remember_feature(&app, &id, enabled.then_some(params)) {
+    log::warn!("Failed to remember feature {id}: {error}");
+  }
+  Ok(())
 }
 
 #[tauri::command]
-pub(crate) fn apply_nop(
+pub(crate) fn read_memory(
   state: State<'_, ProcessState>,
-  offset: u64,
+  session: Option<u32>,
+  offset: Target,
   size: u64,
-  enabled: bool,
+) -> Result<Vec<u8>, String> {
+  let size = usize::try_from(size)
+    .ok()
+    .filter(|size| *size <= MAX_READ_LEN)
+    .ok_or_else(|| format!("Reads are at most {MAX_READ_LEN:#x} bytes"))?;
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  let address = handle.resolve_target(&offset, size)?;
+  handle.backend.read(address, size)
+}
+
+/// Reads a typed value at an RVA or pointer path; little-endian by default.
+#[tauri::command]
+pub(crate) fn read_value(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  target: Target,
+  value_type: ValueType,
+  endian: Option<Endian>,
+) -> Result<Value, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  let probe = Probe {
+    target,
+    value_type,
+    endian: endian.unwrap_or_default(),
+  };
+  probe.read(handle)
+}
+
+/// Writes a typed value once; see [`freeze::write`].
+#[tauri::command]
+pub(crate) fn write_value(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  target: Target,
+  value: Value,
+  endian: Option<Endian>,
 ) -> Result<(), String> {
-  let mut guard = state.inner.lock().map_err(|_| "State lock error".to_string())?;
-  let handle = guard.as_mut().ok_or_else(|| "Process not attached".to_string())?;
-  let address = handle.base + offset as usize;
-  let bytes = vec![0x90; size as usize];
-  let threads = suspend_process_threads(handle.pid);
-  let result = apply_patch_at(handle, address, &bytes, enabled);
-  resume_process_threads(threads);
-  result
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  freeze::write(handle, &target, &value, endian.unwrap_or_default())
 }
 
+/// Keeps writing `value` to `target` every `interval_ms` until unfrozen or the
+/// session is detached. Returns the freeze id.
 #[tauri::command]
-pub(crate) fn apply_float(
+pub(crate) fn freeze_value(
+  app: AppHandle,
   state: State<'_, ProcessState>,
-  offset: u64,
-  value: f32,
-  enabled: bool,
+  session: Option<u32>,
+  target: Target,
+  value: Value,
+  endian: Option<Endian>,
+  interval_ms: Option<u64>,
+) -> Result<u32, String> {
+  let mut guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session_mut(&mut guard, session)?;
+  let interval_ms = interval_ms.unwrap_or(freeze::DEFAULT_INTERVAL_MS);
+  freeze::freeze(&app, handle, target, value, endian.unwrap_or_default(), interval_ms)
+}
+
+#[tauri::command]
+pub(crate) fn unfreeze_value(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  id: u32,
 ) -> Result<(), String> {
-  let mut guard = state.inner.lock().map_err(|_| "State lock error".to_string())?;
-  let handle = guard.as_mut().ok_or_else(|| "Process not attached".to_string())?;
-  let address = handle.base + offset as usize;
-  let bytes = value.to_le_bytes();
-  apply_patch_at(handle, address, &bytes, enabled)
+  let mut guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session_mut(&mut guard, session)?;
+  handle
+    .freezes
+    .remove(&id)
+    .map(|_| ())
+    .ok_or_else(|| format!("No frozen value {id}"))
+}
+
+#[tauri::command]
+pub(crate) fn list_frozen(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+) -> Result<Vec<Freeze>, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  Ok(handle.freezes.values().cloned().collect())
+}
+
+/// Starts emitting `telemetry` events for `session` every `tick_ms`. `values`
+/// defaults to every value the session's profile declares. Returns the
+/// subscription id for `unsubscribe_telemetry`.
+#[tauri::command]
+pub(crate) fn subscribe_telemetry(
+  app: AppHandle,
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  values: Option<BTreeMap<String, Probe>>,
+  tick_ms: Option<u64>,
+) -> Result<u32, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  let values = values.unwrap_or_else(|| handle.profile.values.clone());
+  let pid = handle.pid;
+  drop(guard);
+  let tick_ms = tick_ms.unwrap_or(telemetry::DEFAULT_TICK_MS);
+  telemetry::subscribe(&app, pid, values, tick_ms)
+}
+
+#[tauri::command]
+pub(crate) fn unsubscribe_telemetry(id: u32) -> Result<(), String> {
+  if telemetry::unsubscribe(id) {
+    Ok(())
+  } else {
+    Err(format!("No telemetry subscription {id}"))
+  }
+}
+
+/// The session's address space, region by region.
+#[tauri::command]
+pub(crate) fn list_regions(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+) -> Result<Vec<MemoryRegion>, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  Ok(handle.backend.regions())
+}
+
+#[tauri::command]
+pub(crate) fn list_game_processes() -> Vec<GameProcess> {
+  backend::list_game_processes()
 }
 
+/// Attaches to `pid`, or to the first Sky.exe found if none is given. The pid
+/// is the id of the new session; other sessions are left alone.
 #[tauri::command]
-pub(crate) fn attach_process(state: State<'_, ProcessState>) -> Result<AttachResponse, String> {
-  let pid = find_pid_by_window_class(SKY_WINDOW_CLASS)
-    .or_else(|| find_pid_by_name(SKY_EXE))
+pub(crate) fn attach_process(
+  app: AppHandle,
+  state: State<'_, ProcessState>,
+  pid: Option<u32>,
+) -> Result<AttachResponse, String> {
+  let pid = pid
+    .or_else(backend::find_game_pid)
     .ok_or_else(|| "Sky.exe not found".to_string())?;
+  attach(&app, &state, pid)
+}
 
-  let base = get_module_base(pid, SKY_EXE).ok_or_else(|| "Failed to find module base".to_string())?;
-  let handle = unsafe {
-    OpenProcess(
-      PROCESS_QUERY_INFORMATION
-        | PROCESS_CREATE_THREAD
-        | PROCESS_VM_READ
-        | PROCESS_VM_WRITE
-        | PROCESS_VM_OPERATION,
-      0,
-      pid,
-    )
-  };
-  if handle == 0 {
-    return Err("Failed to open Sky.exe process".to_string());
+/// Opens `pid`, selects its offset profile, resolves every site and adds it as
+/// a session. An earlier session for the same pid is released first, putting
+/// its patches back even if patches are normally kept, so signatures and site
+/// checks see the game's own bytes. Shared by `attach_process` and
+/// auto-attach.
+pub(crate) fn attach(
+  app: &AppHandle,
+  state: &ProcessState,
+  pid: u32,
+) -> Result<AttachResponse, String> {
+  let mut guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let previous = guard.remove(&pid);
+  drop(guard);
+  if let Some(mut previous) = previous {
+    for failure in process::release(&mut previous, false).failed {
+      log::warn!("Failed to restore Sky.exe+{:#x} in {pid}: {}", failure.offset, failure.error);
+    }
   }
 
-  let mut guard = state.inner.lock().map_err(|_| "State lock error".to_string())?;
-  let process = ProcessHandle {
-    handle,
-    pid,
-    base,
-    original: std::collections::HashMap::new(),
-  };
-  *guard = Some(process);
+  let backend = backend::open(pid)?;
+  let module = backend
+    .module(SKY_EXE)
+    .ok_or_else(|| "Failed to find module base".to_string())?;
 
-  Ok(AttachResponse { pid, base })
+  let image = PeImage::from_remote(backend.as_ref(), module.base)
+    .or_else(|error| PeImage::from_file(Path::new(&module.path)).map_err(|_| error))?;
+  let build = BuildIdentity::new(&image, &module.path);
+  let active = profile::select_profile(profile::load_profiles(app), &build)
+    .ok_or_else(|| "No offset profile matches this Sky.exe build".to_string())?;
+
+  let mut process = ProcessHandle::new(backend, module.base, active);
+  process.sites =
+    process::resolve_sites(process.backend.as_ref(), &module, &image, &process.profile);
+  process.build = Some(build);
+  process.image = Some(image);
+  let mut unresolved: Vec<UnresolvedSite> = process
+    .sites
+    .iter()
+    .filter_map(|(name, site)| {
+      let error = site.as_ref().err()?;
+      Some(UnresolvedSite {
+        name: name.clone(),
+        error: error.clone(),
+      })
+    })
+    .collect();
+  unresolved.sort_by(|a, b| a.name.cmp(&b.name));
+
+  let profile_name = process.profile.name.clone();
+  let mut guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  if let Some(mut raced) = guard.insert(pid, process) {
+    process::release(&mut raced, false);
+  }
+  drop(guard);
+  watcher::spawn_exit_watcher(app.clone(), pid);
+
+  Ok(AttachResponse {
+    pid,
+    base: module.base,
+    profile: profile_name,
+    unresolved,
+  })
 }
 
+/// Detaches from one session, restoring every patched site first unless
+/// `keep_patches` (or the matching setting) asks to leave them applied.
 #[tauri::command]
-pub(crate) fn detach_process(state: State<'_, ProcessState>) -> Result<(), String> {
-  let mut guard = state.inner.lock().map_err(|_| "State lock error".to_string())?;
-  let _ = guard.take();
-  Ok(())
+pub(crate) fn detach_process(
+  app: AppHandle,
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  keep_patches: Option<bool>,
+) -> Result<DetachResponse, String> {
+  let keep_patches =
+    keep_patches.unwrap_or_else(|| settings::current_settings(&app).keep_patches_on_detach);
+  let mut guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  if guard.is_empty() {
+    return Ok(DetachResponse::default());
+  }
+  let pid = session_id(&guard, session)?;
+  let Some(mut handle) = guard.remove(&pid) else {
+    return Ok(DetachResponse::default());
+  };
+  watcher::suppress_auto_attach(handle.pid);
+  Ok(process::release(&mut handle, keep_patches))
+}
+
+/// Releases every session when the window closes or the app exits. Nothing
+/// can be shown to the user at that point, so failures are logged.
+pub(crate) fn release_on_exit(app: &AppHandle) {
+  let keep_patches = settings::current_settings(app).keep_patches_on_detach;
+  let state = app.state::<ProcessState>();
+  let Ok(mut guard) = state.sessions.lock() else {
+    return;
+  };
+  for (pid, mut handle) in std::mem::take(&mut *guard) {
+    for failure in process::release(&mut handle, keep_patches).failed {
+      log::warn!("Failed to restore Sky.exe+{:#x} in {pid}: {}", failure.offset, failure.error);
+    }
+  }
 }
 
 #[tauri::command]
 pub(crate) fn status(state: State<'_, ProcessState>) -> Result<StatusResponse, String> {
-  let guard = state.inner.lock().map_err(|_| "State lock error".to_string())?;
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
   Ok(StatusResponse {
-    attached: guard.is_some(),
-    pid: guard.as_ref().map(|handle| handle.pid),
+    attached: !guard.is_empty(),
+    pid: find_session(&guard, None).ok().map(|handle| handle.pid),
+    sessions: guard
+      .values()
+      .map(|handle| SessionInfo {
+        pid: handle.pid,
+        profile: handle.profile.name.clone(),
+        active: handle.active.keys().cloned().collect(),
+      })
+      .collect(),
   })
 }
 
+/// Closes the client of `session`, or the first Sky.exe found when detached.
 #[tauri::command]
-pub(crate) fn close_game() -> Result<(), String> {
-  let pid = find_pid_by_window_class(SKY_WINDOW_CLASS)
-    .or_else(|| find_pid_by_name(SKY_EXE))
+pub(crate) fn close_game(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+) -> Result<(), String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let attached = optional_session(&guard, session)?.map(|handle| handle.pid);
+  drop(guard);
+  let pid = attached
+    .or_else(backend::find_game_pid)
     .ok_or_else(|| "Sky.exe not found".to_string())?;
-  let handle = unsafe { OpenProcess(PROCESS_TERMINATE, 0, pid) };
-  if handle == 0 {
-    return Err("Failed to open Sky.exe process".to_string());
-  }
-  let result = unsafe { TerminateProcess(handle, 0) };
-  unsafe {
-    CloseHandle(handle);
-  }
-  if result == 0 {
-    return Err("Failed to close Sky.exe".to_string());
-  }
-  Ok(())
+  backend::terminate(pid)
 }
 
 #[tauri::command]
 pub(crate) fn launch_game() -> Result<(), String> {
-  let status = std::process::Command::new("cmd")
-    .args(["/C", "start", "", "steam://rungameid/2325290"])
+  #[cfg(windows)]
+  let mut command = {
+    let mut command = std::process::Command::new("cmd");
+    command.args(["/C", "start", "", STEAM_RUN_URL]);
+    command
+  };
+  #[cfg(target_os = "linux")]
+  let mut command = {
+    let mut command = std::process::Command::new("xdg-open");
+    command.arg(STEAM_RUN_URL);
+    command
+  };
+  let status = command
     .status()
     .map_err(|_| "Failed to launch Steam".to_string())?;
   if !status.success() {
@@ -144,68 +387,80 @@ pub(crate) fn launch_game() -> Result<(), String> {
 }
 
 #[tauri::command]
-pub(crate) fn get_foreground_window_class() -> Result<ForegroundWindow, String> {
-  let class_name = foreground_window_class().ok_or_else(|| "No foreground window".to_string())?;
-  Ok(ForegroundWindow { class_name })
+pub(crate) fn get_module_info(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+) -> Result<PeImage, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  handle.image.clone().ok_or_else(|| "Module layout unavailable".to_string())
 }
 
 #[tauri::command]
-pub(crate) fn get_offsets() -> OffsetsResponse {
-  OffsetsResponse {
-    invincibility: OFFSET_INVINCIBILITY as u64,
-    run_speed: OFFSET_RUN_SPEED as u64,
-    default_run_speed: DEFAULT_RUN_SPEED,
-    infinite_energy: OFFSET_INFINITE_ENERGY as u64,
-    infinite_breath: OFFSET_INFINITE_BREATH as u64,
-    anti_rain_drain: OFFSET_ANTI_RAIN_DRAIN as u64,
-    anti_afk: OFFSET_ANTI_AFK as u64,
-    super_jump: OFFSET_SUPER_JUMP as u64,
-    super_swim: OFFSET_SUPER_SWIM as u64,
-    super_flight: OFFSET_SUPER_FLIGHT as u64,
-    anti_sink: OFFSET_ANTI_SINK as u64,
-    disable_cam_snap: OFFSET_DISABLE_CAM_SNAP as u64,
-    free_zoom: OFFSET_FREE_ZOOM as u64,
-    disable_cam_rotation: OFFSET_DISABLE_CAM_ROTATION as u64,
-    first_person: OFFSET_FIRST_PERSON as u64,
-    show_cursor: OFFSET_SHOW_CURSOR as u64,
-    super_run_patch: OFFSET_SUPER_RUN_PATCH as u64,
-  }
+pub(crate) fn get_foreground_window_class() -> Result<ForegroundWindow, String> {
+  let class_name = foreground_window_class()?;
+  Ok(ForegroundWindow { class_name })
 }
 
+/// The offset profile in use. While attached this is the profile selected for
+/// the session's build, with offsets the scanner resolved; sites that failed to
+/// resolve are `null` so the UI cannot patch them. Detached, it is the bundled
+/// default profile.
 #[tauri::command]
-pub(crate) fn set_invincibility(state: State<'_, ProcessState>, enabled: bool) -> Result<(), String> {
-  let mut guard = state.inner.lock().map_err(|_| "State lock error".to_string())?;
-  let handle = guard.as_mut().ok_or_else(|| "Process not attached".to_string())?;
-  let address = handle.base + OFFSET_INVINCIBILITY;
-
-  if enabled {
-    if !handle.original.contains_key(&address) {
-      let original = super::process::read_bytes(handle.handle, address, 1)?;
-      handle.original.insert(address, original);
-    }
-    super::process::write_bytes(handle.handle, address, &[0x01])?;
-  } else if let Some(original) = handle.original.remove(&address) {
-    super::process::write_bytes(handle.handle, address, &original)?;
-  } else {
-    super::process::write_bytes(handle.handle, address, &[0x00])?;
-  }
-  Ok(())
+pub(crate) fn get_offsets(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+) -> Result<OffsetsResponse, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let Some(handle) = optional_session(&guard, session)? else {
+    let active = profile::default_profile();
+    let offsets = active
+      .sites
+      .iter()
+      .map(|(name, locator)| {
+        let offset = match locator {
+          SiteLocator::Offset(offset) => Some(*offset as u64),
+          SiteLocator::Signature(_) => None,
+        };
+        (name.clone(), offset)
+      })
+      .collect();
+    return Ok(OffsetsResponse {
+      profile: active.name,
+      build: None,
+      default_run_speed: active.default_run_speed,
+      offsets,
+    });
+  };
+  let offsets = handle
+    .profile
+    .sites
+    .keys()
+    .map(|name| {
+      let offset = handle
+        .site(name)
+        .ok()
+        .and_then(|address| address.checked_sub(handle.base))
+        .map(|offset| offset as u64);
+      (name.clone(), offset)
+    })
+    .collect();
+  Ok(OffsetsResponse {
+    profile: handle.profile.name.clone(),
+    build: handle.build.clone(),
+    default_run_speed: handle.profile.default_run_speed,
+    offsets,
+  })
 }
 
-#[tauri::command]
-pub(crate) fn set_run_speed(state: State<'_, ProcessState>, value: f32) -> Result<(), String> {
-  let mut guard = state.inner.lock().map_err(|_| "State lock error".to_string())?;
-  let handle = guard.as_mut().ok_or_else(|| "Process not attached".to_string())?;
-  let address = handle.base + OFFSET_RUN_SPEED;
-  if !handle.original.contains_key(&address) {
-    let original = super::process::read_bytes(handle.handle, address, 4)?;
-    handle.original.insert(address, original);
+/// Like [`find_session`], but `None` instead of an error when nothing is attached
+/// and no session was asked for, for commands that also work detached.
+fn optional_session(
+  sessions: &Sessions,
+  pid: Option<u32>,
+) -> Result<Option<&ProcessHandle>, String> {
+  if sessions.is_empty() && pid.is_none() {
+    return Ok(None);
   }
-  super::process::write_bytes(handle.handle, address, &value.to_le_bytes())?;
-  Ok(())
+  find_session(sessions, pid).map(Some)
 }
-
-#[tauri::command]
-pub(crate) fn reset_run_speed(state: State<'_, ProcessState>) -> Result<(), String> {
-  set_run_speed(state, DEFAULT_RUN_SPEED)
-}
\ No newline at end of file
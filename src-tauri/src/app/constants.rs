@@ -1,23 +1,7 @@
 pub(crate) const SKY_EXE: &str = "Sky.exe";
+#[cfg(windows)]
 pub(crate) const SKY_WINDOW_CLASS: &str = "TgcMainWindow";
-pub(crate) const OFFSET_INVINCIBILITY: usize = 0x2FF40E2;
-pub(crate) const OFFSET_RUN_SPEED: usize = 0x27BDA38;
-pub(crate) const DEFAULT_RUN_SPEED: f32 = 3.5;
-
-pub(crate) const OFFSET_INFINITE_ENERGY: usize = 0x2FF40E1;
-pub(crate) const OFFSET_INFINITE_BREATH: usize = 0x1A5DAD2;
-pub(crate) const OFFSET_ANTI_RAIN_DRAIN: usize = 0x2FF40E6;
-pub(crate) const OFFSET_ANTI_AFK: usize = 0x286B3FC;
-
-pub(crate) const OFFSET_SUPER_JUMP: usize = 0x23117EC;
-pub(crate) const OFFSET_SUPER_SWIM: usize = 0x27BD7E0;
-pub(crate) const OFFSET_SUPER_FLIGHT: usize = 0x00A5C842;
-pub(crate) const OFFSET_ANTI_SINK: usize = 0x27BDA30;
-
-pub(crate) const OFFSET_DISABLE_CAM_SNAP: usize = 0x27A7885;
-pub(crate) const OFFSET_FREE_ZOOM: usize = 0x3579D5;
-pub(crate) const OFFSET_DISABLE_CAM_ROTATION: usize = 0x3530C8;
-pub(crate) const OFFSET_FIRST_PERSON: usize = 0x2311854;
-
-pub(crate) const OFFSET_SHOW_CURSOR: usize = 0x2F96890;
-pub(crate) const OFFSET_SUPER_RUN_PATCH: usize = 0x27BD818;
+pub(crate) const STEAM_RUN_URL: &str = "steam://rungameid/2325290";
+/// Largest buffer a single command reads from the game, so a bad length is
+/// refused instead of aborting the app on allocation.
+pub(crate) const MAX_READ_LEN: usize = 0x10000;
@@ -0,0 +1,178 @@
+use iced_x86::{Decoder, DecoderOptions, Formatter, IntelFormatter};
+use serde::Serialize;
+use tauri::State;
+
+use super::state::{find_session, ProcessHandle, ProcessState};
+
+const MAX_INSTRUCTION_LEN: usize = 15;
+/// Bytes decoded ahead of an address. x86 decoding re-synchronises within a
+/// few instructions, so by the address itself the boundaries are reliable.
+const LOOKBACK: usize = 64;
+const MAX_CONTEXT: usize = 64;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Instruction {
+  address: usize,
+  bytes: Vec<u8>,
+  /// Intel syntax, e.g. `mov dword ptr [rcx],3F800000h`.
+  text: String,
+}
+
+fn bitness(handle: &ProcessHandle) -> u32 {
+  handle.image.as_ref().map_or(64, |image| image.pointer_size() as u32 * 8)
+}
+
+/// Reads `lookback` bytes before `address` and `span` after it, dropping the
+/// lookback if the bytes before `address` are unreadable.
+fn read_window(
+  handle: &ProcessHandle,
+  address: usize,
+  lookback: usize,
+  span: usize,
+) -> Result<(usize, Vec<u8>), String> {
+  let start = address.saturating_sub(lookback);
+  if let Ok(bytes) = handle.backend.read(start, address - start + span) {
+    return Ok((start, bytes));
+  }
+  handle.backend.read(address, span).map(|bytes| (address, bytes))
+}
+
+/// Decodes all of `bytes`, minus a trailing instruction cut off by the end.
+fn decode(bitness: u32, start: usize, bytes: &[u8]) -> Vec<iced_x86::Instruction> {
+  let mut decoder = Decoder::with_ip(bitness, bytes, start as u64, DecoderOptions::NONE);
+  let mut decoded = Vec::new();
+  while decoder.can_decode() {
+    let instruction = decoder.decode();
+    let truncated = instruction.is_invalid() && !decoder.can_decode();
+    if !truncated {
+      decoded.push(instruction);
+    }
+  }
+  decoded
+}
+
+fn end(instruction: &iced_x86::Instruction) -> usize {
+  instruction.next_ip() as usize
+}
+
+/// `before` instructions leading up to `address` and `after` from it on.
+pub(crate) fn disassemble(
+  handle: &ProcessHandle,
+  address: usize,
+  before: usize,
+  after: usize,
+) -> Result<Vec<Instruction>, String> {
+  let (before, after) = (before.min(MAX_CONTEXT), after.clamp(1, MAX_CONTEXT));
+  let lookback = LOOKBACK.max(before * MAX_INSTRUCTION_LEN);
+  let (start, bytes) = read_window(handle, address, lookback, after * MAX_INSTRUCTION_LEN)?;
+  let bitness = bitness(handle);
+  // Use the earliest start point that decodes onto `address`, for the most
+  // context; starting at `address` itself always does.
+  let decoded = (start..=address)
+    .map(|from| decode(bitness, from, &bytes[from - start..]))
+    .find(|decoded| decoded.iter().any(|instruction| instruction.ip() as usize == address))
+    .unwrap_or_default();
+  let at = decoded
+    .iter()
+    .position(|instruction| instruction.ip() as usize == address)
+    .unwrap_or(0);
+
+  let mut formatter = IntelFormatter::new();
+  let window = &decoded[at.saturating_sub(before)..(at + after).min(decoded.len())];
+  Ok(
+    window
+      .iter()
+      .map(|instruction| {
+        let offset = instruction.ip() as usize - start;
+        let mut text = String::new();
+        formatter.format(instruction, &mut text);
+        Instruction {
+          address: instruction.ip() as usize,
+          bytes: bytes[offset..offset + instruction.len()].to_vec(),
+          text,
+        }
+      })
+      .collect(),
+  )
+}
+
+/// Total length of the `count` instructions starting at `address`.
+pub(crate) fn instructions_len(
+  handle: &ProcessHandle,
+  address: usize,
+  count: usize,
+) -> Result<usize, String> {
+  let bytes = handle.backend.read(address, count * MAX_INSTRUCTION_LEN)?;
+  let decoded = decode(bitness(handle), address, &bytes);
+  if let Some(invalid) = decoded.iter().take(count).find(|instruction| instruction.is_invalid()) {
+    return Err(format!("no valid instruction at {:#x}", invalid.ip()));
+  }
+  match decoded.get(..count) {
+    Some(instructions) => Ok(instructions.iter().map(|instruction| instruction.len()).sum()),
+    None => Err(format!("fewer than {count} instructions at {address:#x}")),
+  }
+}
+
+/// Whether `[address, address + len)` lies in an executable section of
+/// Sky.exe.
+pub(crate) fn in_code(handle: &ProcessHandle, address: usize, len: usize) -> bool {
+  let (Some(image), Some(rva)) = (&handle.image, address.checked_sub(handle.base)) else {
+    return false;
+  };
+  image.section_containing(rva, len).is_some_and(|section| section.executable)
+}
+
+/// Rejects a code patch whose end falls inside an instruction. Patch sites
+/// are instruction starts, so decoding runs forward from `address` rather
+/// than guessing a start point before it. Patches outside Sky.exe's
+/// executable sections are not checked.
+pub(crate) fn check_patch(
+  handle: &ProcessHandle,
+  address: usize,
+  len: usize,
+) -> Result<(), String> {
+  if !in_code(handle, address, len) {
+    return Ok(());
+  }
+  let (_, bytes) = read_window(handle, address, 0, len + MAX_INSTRUCTION_LEN)
+    .or_else(|_| read_window(handle, address, 0, len))?;
+  let patch_end = address + len;
+  for instruction in decode(bitness(handle), address, &bytes) {
+    if instruction.is_invalid() {
+      return Err(format!("no valid instruction at {:#x}", instruction.ip()));
+    }
+    match end(&instruction) {
+      end if end == patch_end => return Ok(()),
+      end if end > patch_end => {
+        return Err(format!(
+          "patch at {address:#x} (+{len}) ends inside the {}-byte instruction at {:#x}",
+          instruction.len(),
+          instruction.ip()
+        ));
+      }
+      _ => {}
+    }
+  }
+  Err(format!("could not decode the instructions under the patch at {address:#x}"))
+}
+
+/// Instructions around an address, or around a feature site by name.
+#[tauri::command]
+pub(crate) fn disassemble_at(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  address: Option<usize>,
+  site: Option<String>,
+  before: Option<usize>,
+  after: Option<usize>,
+) -> Result<Vec<Instruction>, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  let address = match (address, site) {
+    (Some(address), _) => address,
+    (None, Some(site)) => handle.site(&site)?,
+    (None, None) => return Err("Pass an address or a site".to_string()),
+  };
+  disassemble(handle, address, before.unwrap_or(8), after.unwrap_or(16))
+}
@@ -0,0 +1,549 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::disasm;
+use super::pointer::Target;
+use super::process;
+use super::profile::OffsetProfile;
+use super::signature::Pattern;
+use super::state::{FeatureError, FeatureOperation, ProcessHandle};
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Category {
+  Player,
+  Movement,
+  Camera,
+  Settings,
+}
+
+/// What an operation writes at its site.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum Write {
+  Patch { bytes: &'static [u8] },
+  /// Bytes written over an instruction's operands rather than from its
+  /// start, so they are not checked against instruction boundaries.
+  Immediate { bytes: &'static [u8] },
+  Nop { size: usize },
+  /// NOPs over the next `count` whole instructions, however long they are.
+  NopInstructions { count: usize },
+  /// Float writes target immediates or data, not instruction starts, so
+  /// they are not checked against instruction boundaries.
+  Float { value: f32 },
+  /// A float taken from one of the feature's parameters.
+  FloatParam { param: &'static str },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OperationDef {
+  pub(crate) site: &'static str,
+  pub(crate) write: Write,
+  /// Masked pattern the site must hold before it is patched, one token per
+  /// written byte. `??` only pins the length; profiles can tighten it per
+  /// build through their `expected` map, and must for code sites whose
+  /// pattern pins nothing.
+  pub(crate) expected: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ParamDef {
+  pub(crate) id: &'static str,
+  pub(crate) label: &'static str,
+  pub(crate) min: f32,
+  pub(crate) max: f32,
+  pub(crate) default: f32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FeatureDef {
+  pub(crate) id: &'static str,
+  pub(crate) label: &'static str,
+  pub(crate) description: &'static str,
+  pub(crate) category: Category,
+  pub(crate) ops: &'static [OperationDef],
+  pub(crate) params: &'static [ParamDef],
+}
+
+const fn patch(site: &'static str, bytes: &'static [u8], expected: &'static str) -> OperationDef {
+  OperationDef {
+    site,
+    write: Write::Patch { bytes },
+    expected,
+  }
+}
+
+const fn immediate(
+  site: &'static str,
+  bytes: &'static [u8],
+  expected: &'static str,
+) -> OperationDef {
+  OperationDef {
+    site,
+    write: Write::Immediate { bytes },
+    expected,
+  }
+}
+
+const fn nop(site: &'static str, size: usize, expected: &'static str) -> OperationDef {
+  OperationDef {
+    site,
+    write: Write::Nop { size },
+    expected,
+  }
+}
+
+const fn nop_instructions(
+  site: &'static str,
+  count: usize,
+  expected: &'static str,
+) -> OperationDef {
+  OperationDef {
+    site,
+    write: Write::NopInstructions { count },
+    expected,
+  }
+}
+
+/// Every feature the backend is willing to write. Sites are looked up in the
+/// active offset profile.
+pub(crate) static FEATURES: &[FeatureDef] = &[
+  FeatureDef {
+    id: "godmode",
+    label: "Godmode",
+    description: "Enables invincibility against all damage sources",
+    category: Category::Player,
+    ops: &[patch("invincibility", &[0x01], "0?")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "infinite-energy",
+    label: "Infinite Energy",
+    description: "Never run out of wing energy",
+    category: Category::Player,
+    ops: &[patch("infiniteEnergy", &[0x01], "0?")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "infinite-breath",
+    label: "Infinite Breath",
+    description: "Never run out of breath underwater",
+    category: Category::Player,
+    ops: &[nop("infiniteBreath", 6, "?? ?? ?? ?? ?? ??")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "anti-rain",
+    label: "Anti Rain Drain",
+    description: "Prevents rain from draining your light",
+    category: Category::Player,
+    ops: &[patch("antiRainDrain", &[0x01], "0?")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "anti-afk",
+    label: "Anti AFK",
+    description: "Prevents entering AFK state when idle",
+    category: Category::Player,
+    ops: &[patch("antiAfk", &[0x00], "??")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "super-jump",
+    label: "Super Jump",
+    description: "Jump further",
+    category: Category::Movement,
+    ops: &[immediate("superJump", &[0x00, 0x00, 0x20, 0x41, 0x9a], "?? ?? ?? ?? ??")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "super-swim",
+    label: "Super Swim",
+    description: "Swim faster",
+    category: Category::Movement,
+    ops: &[immediate("superSwim", &[0x00, 0x00, 0x48, 0x42, 0x6f], "?? ?? ?? ?? ??")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "super-flight",
+    label: "Super Flight",
+    description: "Fly faster",
+    category: Category::Movement,
+    ops: &[patch(
+      "superFlight",
+      &[0xc7, 0x01, 0x00, 0x00, 0xc8, 0x42],
+      "C7 01 ?? ?? ?? ??",
+    )],
+    params: &[],
+  },
+  FeatureDef {
+    id: "anti-sink",
+    label: "Anti Sink",
+    description: "Prevents sinking in water",
+    category: Category::Movement,
+    ops: &[OperationDef {
+      site: "antiSink",
+      write: Write::Float { value: 100.0 },
+      expected: "?? ?? ?? ??",
+    }],
+    params: &[],
+  },
+  FeatureDef {
+    id: "super-run",
+    label: "Super Run",
+    description: "Run really fast",
+    category: Category::Movement,
+    ops: &[
+      patch("superRunPatch", &[0x00], "??"),
+      OperationDef {
+        site: "runSpeed",
+        write: Write::FloatParam { param: "speed" },
+        expected: "?? ?? ?? ??",
+      },
+    ],
+    params: &[ParamDef {
+      id: "speed",
+      label: "Speed",
+      min: 5.0,
+      max: 60.0,
+      default: 20.0,
+    }],
+  },
+  FeatureDef {
+    id: "disable-cam-snap",
+    label: "Disable Camera Snapping",
+    description: "Prevents camera from automatically snapping",
+    category: Category::Camera,
+    ops: &[patch("disableCamSnap", &[0x00], "??")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "free-zoom",
+    label: "Disable Zoom Restrictions",
+    description: "Removes limits on camera zoom",
+    category: Category::Camera,
+    ops: &[nop("freeZoom", 9, "?? ?? ?? ?? ?? ?? ?? ?? ??")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "disable-cam-rotation",
+    label: "Disable Camera Rotation",
+    description: "Prevents camera from rotating",
+    category: Category::Camera,
+    ops: &[nop("disableCamRotation", 2, "?? ??")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "first-person",
+    label: "First Person",
+    description: "Enables first-person camera mode",
+    category: Category::Camera,
+    ops: &[nop_instructions("firstPerson", 1, "E8 ?? ?? ?? ??")],
+    params: &[],
+  },
+  FeatureDef {
+    id: "show-cursor",
+    label: "Show Cursor",
+    description: "Keeps the system cursor visible while in-game.",
+    category: Category::Settings,
+    ops: &[patch("showCursor", &[0x01], "0?")],
+    params: &[],
+  },
+];
+
+pub(crate) fn find(id: &str) -> Result<&'static FeatureDef, String> {
+  FEATURES
+    .iter()
+    .find(|feature| feature.id == id)
+    .ok_or_else(|| format!("Unknown feature {id}"))
+}
+
+/// The pattern `op`'s site must hold before it is patched: the profile's
+/// override for the site, or the registry's.
+fn expected_pattern(op: &OperationDef, profile: &OffsetProfile) -> Result<Pattern, String> {
+  Pattern::parse(profile.expected.get(op.site).map_or(op.expected, String::as_str))
+}
+
+/// Why `feature` cannot be used, if it can't. Attached, every site must have
+/// resolved; detached, the profile only has to define them. Either way a code
+/// site needs pinned original bytes, which attached is decided by the section
+/// the site resolved to and detached by whether the operation NOPs or
+/// rewrites code.
+pub(crate) fn availability(
+  feature: &FeatureDef,
+  handle: Option<&ProcessHandle>,
+  profile: &OffsetProfile,
+) -> Result<(), String> {
+  for op in feature.ops {
+    let expected = expected_pattern(op, profile)?;
+    let code = match handle {
+      Some(handle) => {
+        let address = handle.site(op.site)?;
+        disasm::in_code(handle, address, expected.len())
+      }
+      None if !profile.sites.contains_key(op.site) => {
+        return Err(format!("Unknown feature site {}", op.site));
+      }
+      None => matches!(
+        op.write,
+        Write::Immediate { .. } | Write::Nop { .. } | Write::NopInstructions { .. }
+      ),
+    };
+    if code && expected.is_wildcard() {
+      return Err(format!("No original bytes are pinned for code site {}", op.site));
+    }
+  }
+  Ok(())
+}
+
+fn param_value(
+  feature: &FeatureDef,
+  name: &str,
+  params: &HashMap<String, f32>,
+) -> Result<f32, String> {
+  let def = feature
+    .params
+    .iter()
+    .find(|param| param.id == name)
+    .ok_or_else(|| format!("{} has no parameter {name}", feature.id))?;
+  let value = params.get(name).copied().unwrap_or(def.default);
+  if !(def.min..=def.max).contains(&value) {
+    return Err(format!("{} must be between {} and {}", def.label, def.min, def.max));
+  }
+  Ok(value)
+}
+
+/// Enables or disables a registered feature. Site checks and rollback happen
+/// in [`process::apply_feature`].
+pub(crate) fn set_feature(
+  handle: &mut ProcessHandle,
+  id: &str,
+  enabled: bool,
+  params: &HashMap<String, f32>,
+) -> Result<(), FeatureError> {
+  let feature = find(id)?;
+  if let Some(unknown) = params.keys().find(|name| !feature.params.iter().any(|p| p.id == *name)) {
+    return Err(format!("{} has no parameter {unknown}", feature.id).into());
+  }
+
+  let mut operations = Vec::with_capacity(feature.ops.len());
+  for (index, op) in feature.ops.iter().enumerate() {
+    let fail = |message: String| FeatureError {
+      operation: Some(index),
+      message: format!("{} ({}): {message}", feature.label, op.site),
+      unreverted: Vec::new(),
+    };
+    let address = handle.site(op.site).map_err(fail)?;
+    let bytes = match op.write {
+      Write::Patch { bytes } | Write::Immediate { bytes } => bytes.to_vec(),
+      Write::Nop { size } => vec![0x90; size],
+      // Once patched the site is all NOPs, so the length comes from what was
+      // recorded rather than decoding again.
+      Write::NopInstructions { count } => match handle.patches.get(&address) {
+        Some(record) => vec![0x90; record.original.len()],
+        None => vec![0x90; disasm::instructions_len(handle, address, count).map_err(fail)?],
+      },
+      Write::Float { value } => value.to_le_bytes().to_vec(),
+      Write::FloatParam { param } => param_value(feature, param, params)
+        .map_err(fail)?
+        .to_le_bytes()
+        .to_vec(),
+    };
+    let expected = expected_pattern(op, &handle.profile).map_err(fail)?;
+    if expected.len() != bytes.len() {
+      return Err(fail(format!("expected pattern `{expected}` does not cover the patch")));
+    }
+    let immediate = matches!(
+      op.write,
+      Write::Immediate { .. } | Write::Float { .. } | Write::FloatParam { .. }
+    );
+    if enabled && !immediate && !handle.patches.contains_key(&address) {
+      disasm::check_patch(handle, address, bytes.len()).map_err(fail)?;
+    }
+    let offset = address
+      .checked_sub(handle.base)
+      .ok_or_else(|| fail(format!("site {address:#x} resolved below Sky.exe")))?;
+    if enabled && expected.is_wildcard() && disasm::in_code(handle, address, bytes.len()) {
+      return Err(fail("no original bytes are pinned for this code site".to_string()));
+    }
+    operations.push(FeatureOperation {
+      site: op.site.to_string(),
+      target: Target::Offset(offset as u64),
+      bytes,
+      expected,
+    });
+  }
+  process::apply_feature(handle, feature, &operations, enabled)?;
+  if enabled {
+    handle.active.insert(feature.id.to_string(), params.clone());
+  } else {
+    handle.active.remove(feature.id);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::backend::fake::{FakeProcess, SKY_BASE};
+  use crate::app::backend::MemoryBackend;
+
+  fn toggle(handle: &mut ProcessHandle, id: &str, enabled: bool) -> Result<(), String> {
+    set_feature(handle, id, enabled, &HashMap::new()).map_err(|error| error.message)
+  }
+
+  #[test]
+  fn patches_and_restores_a_code_site() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[("superFlight", 0x210)]);
+    toggle(&mut handle, "super-flight", true).unwrap();
+    assert_eq!(process.bytes(SKY_BASE + 0x210, 6), [0xC7, 0x01, 0x00, 0x00, 0xC8, 0x42]);
+    assert!(handle.active.contains_key("super-flight"));
+
+    toggle(&mut handle, "super-flight", false).unwrap();
+    assert_eq!(process.bytes(SKY_BASE + 0x210, 6), [0xC7, 0x01, 0x00, 0x00, 0x80, 0x3F]);
+    assert!(handle.patches.is_empty() && handle.active.is_empty());
+    assert!(!process.is_suspended());
+  }
+
+  #[test]
+  fn refuses_a_site_that_does_not_match() {
+    let process = FakeProcess::sky(1);
+    // 0xD2, the low byte of the counter in .data, is not `0?`.
+    let mut handle = process.attach(&[("invincibility", 0x600)]);
+    let error = toggle(&mut handle, "godmode", true).unwrap_err();
+    assert!(error.contains("site mismatch"), "{error}");
+    assert_eq!(process.bytes(SKY_BASE + 0x600, 1), [0xD2]);
+  }
+
+  #[test]
+  fn refuses_a_site_that_is_already_patched() {
+    let process = FakeProcess::sky(1);
+    process.write(SKY_BASE + 0x214, &[0xC8, 0x42]).unwrap();
+    let mut handle = process.attach(&[("superFlight", 0x210)]);
+    let error = toggle(&mut handle, "super-flight", true).unwrap_err();
+    assert!(error.contains("already patched"), "{error}");
+    assert!(handle.patches.is_empty());
+  }
+
+  #[test]
+  fn requires_pinned_bytes_at_code_sites() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[("antiAfk", 0x216)]);
+    let error = toggle(&mut handle, "anti-afk", true).unwrap_err();
+    assert!(error.contains("no original bytes are pinned"), "{error}");
+    assert_eq!(process.bytes(SKY_BASE + 0x216, 1), [0x90]);
+  }
+
+  #[test]
+  fn enables_a_flag_that_already_holds_the_patch() {
+    let process = FakeProcess::sky(1);
+    // The byte at 0x608 is already 0x01, which is also what godmode writes.
+    let mut handle = process.attach(&[("invincibility", 0x608)]);
+    toggle(&mut handle, "godmode", true).unwrap();
+    assert_eq!(process.bytes(SKY_BASE + 0x608, 1), [0x01]);
+    toggle(&mut handle, "godmode", false).unwrap();
+    assert_eq!(process.bytes(SKY_BASE + 0x608, 1), [0x01]);
+    assert!(handle.patches.is_empty());
+  }
+
+  #[test]
+  fn reports_unpinned_code_sites_as_unavailable() {
+    let process = FakeProcess::sky(1);
+    let anti_afk = find("anti-afk").unwrap();
+    let handle = process.attach(&[("antiAfk", 0x216)]);
+    let error = availability(anti_afk, Some(&handle), &handle.profile).unwrap_err();
+    assert!(error.contains("No original bytes are pinned"), "{error}");
+    let handle = process.attach(&[("antiAfk", 0x60A)]);
+    assert!(availability(anti_afk, Some(&handle), &handle.profile).is_ok());
+
+    // Detached, NOPs are known to target code; other writes can't be told apart.
+    let mut profile = handle.profile.clone();
+    let infinite_breath = find("infinite-breath").unwrap();
+    assert!(availability(infinite_breath, None, &profile).is_err());
+    assert!(availability(anti_afk, None, &profile).is_ok());
+    profile.expected.insert("infiniteBreath".to_string(), "0F 2F ?? ?? ?? ??".to_string());
+    assert!(availability(infinite_breath, None, &profile).is_ok());
+  }
+
+  #[test]
+  fn writes_immediates_without_decoding_from_the_site() {
+    let process = FakeProcess::sky(1);
+    // `mov dword [rcx], 25.0; nop`. Decoding from the imm32 at 0x212 reads
+    // `add [rax], al; enter ...`, whose second instruction runs past the nop.
+    process.write(SKY_BASE + 0x214, &[0xC8, 0x41]).unwrap();
+    let mut handle = process.attach(&[("superJump", 0x212)]);
+    assert!(disasm::check_patch(&handle, SKY_BASE + 0x212, 5).is_err());
+    let error = toggle(&mut handle, "super-jump", true).unwrap_err();
+    assert!(error.contains("no original bytes are pinned"), "{error}");
+
+    handle.profile.expected.insert("superJump".to_string(), "00 00 C8 41 90".to_string());
+    toggle(&mut handle, "super-jump", true).unwrap();
+    assert_eq!(process.bytes(SKY_BASE + 0x210, 7), [0xC7, 0x01, 0x00, 0x00, 0x20, 0x41, 0x9A]);
+    toggle(&mut handle, "super-jump", false).unwrap();
+    assert_eq!(process.bytes(SKY_BASE + 0x210, 7), [0xC7, 0x01, 0x00, 0x00, 0xC8, 0x41, 0x90]);
+  }
+
+  #[test]
+  fn nops_whole_instructions() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[("firstPerson", 0x207)]);
+    toggle(&mut handle, "first-person", true).unwrap();
+    assert_eq!(process.bytes(SKY_BASE + 0x207, 6), [0x90, 0x90, 0x90, 0x90, 0x90, 0xF3]);
+    toggle(&mut handle, "first-person", false).unwrap();
+    assert_eq!(process.bytes(SKY_BASE + 0x207, 5), [0xE8, 0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn refuses_a_nop_that_splits_an_instruction() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[("infiniteBreath", 0x200)]);
+    let error = toggle(&mut handle, "infinite-breath", true).unwrap_err();
+    assert!(error.contains("ends inside the 7-byte instruction"), "{error}");
+    assert_eq!(process.bytes(SKY_BASE + 0x200, 1), [0x48]);
+  }
+
+  #[test]
+  fn features_share_identical_patches() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[("invincibility", 0x60A), ("infiniteEnergy", 0x60A)]);
+    toggle(&mut handle, "godmode", true).unwrap();
+    toggle(&mut handle, "infinite-energy", true).unwrap();
+    assert_eq!(handle.patches[&(SKY_BASE + 0x60A)].owners.len(), 2);
+
+    toggle(&mut handle, "godmode", false).unwrap();
+    assert_eq!(process.bytes(SKY_BASE + 0x60A, 1), [0x01], "still held by infinite-energy");
+    toggle(&mut handle, "infinite-energy", false).unwrap();
+    assert_eq!(process.bytes(SKY_BASE + 0x60A, 1), [0x00]);
+    assert!(handle.patches.is_empty());
+  }
+
+  #[test]
+  fn refuses_overlapping_and_conflicting_patches() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[("superJump", 0x610), ("antiAfk", 0x612)]);
+    toggle(&mut handle, "super-jump", true).unwrap();
+    let error = toggle(&mut handle, "anti-afk", true).unwrap_err();
+    assert!(error.contains("overlaps the patch"), "{error}");
+    assert_eq!(process.bytes(SKY_BASE + 0x612, 1), [0x20]);
+
+    let mut handle = process.attach(&[("invincibility", 0x60A), ("antiAfk", 0x60A)]);
+    toggle(&mut handle, "godmode", true).unwrap();
+    let error = toggle(&mut handle, "anti-afk", true).unwrap_err();
+    assert!(error.contains("already patched differently by godmode"), "{error}");
+    assert_eq!(process.bytes(SKY_BASE + 0x60A, 1), [0x01]);
+  }
+
+  #[test]
+  fn rejects_sites_below_the_module() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[]);
+    handle.sites.insert("invincibility".to_string(), Ok(SKY_BASE - 0x10));
+    let error = toggle(&mut handle, "godmode", true).unwrap_err();
+    assert!(error.contains("resolved below Sky.exe"), "{error}");
+  }
+}
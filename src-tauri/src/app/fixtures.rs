@@ -0,0 +1,3 @@
+/// A minimal x64 image, mapped and on disk alike: `.text` (code at 0x200),
+/// `.rdata`, `.data` with no virtual size and a discardable `.reloc`.
+pub(crate) const TINY_EXE: &[u8] = include_bytes!("../../fixtures/tiny.exe");
@@ -0,0 +1,198 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use super::pointer::Target;
+use super::state::{ProcessHandle, ProcessState, Sessions};
+use super::value::{Endian, Value};
+
+pub(crate) const DEFAULT_INTERVAL_MS: u64 = 100;
+const MIN_INTERVAL_MS: u64 = 10;
+
+/// Freeze ids are never reused, so a worker can't pick up a later freeze that
+/// happens to share an id with the one it was started for.
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// A value rewritten on an interval until it is unfrozen or its session ends.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Freeze {
+  pub(crate) id: u32,
+  pub(crate) target: Target,
+  pub(crate) value: Value,
+  pub(crate) endian: Endian,
+  pub(crate) interval_ms: u64,
+  /// Why the last write failed, e.g. a pointer path that is null on a loading
+  /// screen. Writes keep being retried.
+  pub(crate) last_error: Option<String>,
+}
+
+/// Writes `value` once, then registers it on `handle` and starts a worker
+/// that keeps rewriting it. Returns the freeze id.
+pub(crate) fn freeze(
+  app: &AppHandle,
+  handle: &mut ProcessHandle,
+  target: Target,
+  value: Value,
+  endian: Endian,
+  interval_ms: u64,
+) -> Result<u32, String> {
+  let id = register(handle, target, value, endian, interval_ms)?;
+  spawn_worker(app.clone(), handle.pid, id);
+  Ok(id)
+}
+
+fn register(
+  handle: &mut ProcessHandle,
+  target: Target,
+  value: Value,
+  endian: Endian,
+  interval_ms: u64,
+) -> Result<u32, String> {
+  write(handle, &target, &value, endian)?;
+  let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+  handle.freezes.insert(
+    id,
+    Freeze {
+      id,
+      target,
+      value,
+      endian,
+      interval_ms: interval_ms.max(MIN_INTERVAL_MS),
+      last_error: None,
+    },
+  );
+  Ok(id)
+}
+
+/// Writes `value` to `target`, encoded with `endian`. Code is refused: patches
+/// to Sky.exe's executable sections only go through features, which record
+/// the original bytes.
+pub(crate) fn write(
+  handle: &ProcessHandle,
+  target: &Target,
+  value: &Value,
+  endian: Endian,
+) -> Result<(), String> {
+  let bytes = value.to_bytes(endian);
+  let address = handle.resolve_target(target, bytes.len())?;
+  let code = handle.image.as_ref().and_then(|image| {
+    let rva = address.checked_sub(handle.base)?;
+    image.sections.iter().find(|section| {
+      section.executable
+        && rva < section.end()
+        && rva + bytes.len() > section.virtual_address as usize
+    })
+  });
+  if let Some(section) = code {
+    return Err(format!("{address:#x} is in {}; use a feature to patch code", section.name));
+  }
+  handle.backend.write(address, &bytes)
+}
+
+/// Rewrites freeze `id` of session `pid` until it is removed from the
+/// session, or the session itself is detached or exits.
+fn spawn_worker(app: AppHandle, pid: u32, id: u32) {
+  thread::spawn(move || loop {
+    let state = app.state::<ProcessState>();
+    let Some(interval) = tick(&state.sessions, pid, id) else {
+      return;
+    };
+    thread::sleep(Duration::from_millis(interval));
+  });
+}
+
+/// One rewrite of freeze `id`, recording its error. Returns how long to wait
+/// before the next, or `None` once the freeze or its session is gone.
+fn tick(sessions: &Mutex<Sessions>, pid: u32, id: u32) -> Option<u64> {
+  let mut guard = sessions.lock().ok()?;
+  let handle = guard.get_mut(&pid)?;
+  let freeze = handle.freezes.get(&id)?;
+  let error = write(handle, &freeze.target, &freeze.value, freeze.endian).err();
+  let freeze = handle.freezes.get_mut(&id)?;
+  freeze.last_error = error;
+  Some(freeze.interval_ms)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeMap;
+
+  use super::*;
+  use crate::app::backend::fake::{FakeProcess, SKY_BASE};
+  use crate::app::backend::MemoryBackend;
+  use crate::app::pointer::PointerPath;
+
+  const HEAP: usize = 0x5000_0000;
+
+  fn sessions(process: &FakeProcess) -> Mutex<Sessions> {
+    Mutex::new(BTreeMap::from([(1, process.attach(&[]))]))
+  }
+
+  fn add(sessions: &Mutex<Sessions>, target: Target, value: Value) -> Result<u32, String> {
+    let mut guard = sessions.lock().unwrap();
+    register(guard.get_mut(&1).unwrap(), target, value, Endian::Little, 5)
+  }
+
+  #[test]
+  fn rewrites_a_frozen_heap_value() {
+    let process = FakeProcess::sky(1).with_region(HEAP, vec![0; 0x100]);
+    let sessions = sessions(&process);
+    let id = add(&sessions, Target::Address(HEAP + 8), Value::F32(20.0)).unwrap();
+    assert_eq!(process.bytes(HEAP + 8, 4), 20f32.to_le_bytes());
+
+    process.write(HEAP + 8, &3.5f32.to_le_bytes()).unwrap();
+    assert_eq!(tick(&sessions, 1, id), Some(MIN_INTERVAL_MS));
+    assert_eq!(process.bytes(HEAP + 8, 4), 20f32.to_le_bytes());
+    assert!(sessions.lock().unwrap()[&1].freezes[&id].last_error.is_none());
+  }
+
+  #[test]
+  fn keeps_retrying_and_reports_write_errors() {
+    let process = FakeProcess::sky(1);
+    let sessions = sessions(&process);
+    let id = add(&sessions, Target::Offset(0x600), Value::U32(7)).unwrap();
+    // Point the freeze at the null pointer in .data, as on a loading screen.
+    let null = PointerPath {
+      module: None,
+      base: 0x60C,
+      offsets: vec![0x18],
+    };
+    let mut guard = sessions.lock().unwrap();
+    guard.get_mut(&1).unwrap().freezes.get_mut(&id).unwrap().target = Target::Pointer(null);
+    drop(guard);
+
+    assert_eq!(tick(&sessions, 1, id), Some(MIN_INTERVAL_MS));
+    let guard = sessions.lock().unwrap();
+    let error = guard[&1].freezes[&id].last_error.clone().unwrap();
+    assert!(error.contains("null pointer"), "{error}");
+  }
+
+  #[test]
+  fn stops_once_unfrozen_or_detached() {
+    let process = FakeProcess::sky(1);
+    let sessions = sessions(&process);
+    let id = add(&sessions, Target::Offset(0x600), Value::U32(7)).unwrap();
+    let other = add(&sessions, Target::Offset(0x600), Value::U32(7)).unwrap();
+    assert_ne!(id, other);
+
+    sessions.lock().unwrap().get_mut(&1).unwrap().freezes.remove(&id);
+    assert_eq!(tick(&sessions, 1, id), None);
+    assert!(tick(&sessions, 1, other).is_some());
+    sessions.lock().unwrap().remove(&1);
+    assert_eq!(tick(&sessions, 1, other), None);
+  }
+
+  #[test]
+  fn refuses_to_freeze_code() {
+    let process = FakeProcess::sky(1);
+    let sessions = sessions(&process);
+    let error = add(&sessions, Target::Address(SKY_BASE + 0x210), Value::U8(0)).unwrap_err();
+    assert!(error.contains("use a feature to patch code"), "{error}");
+    assert!(sessions.lock().unwrap()[&1].freezes.is_empty());
+  }
+}
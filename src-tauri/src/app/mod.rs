@@ -1,12 +1,29 @@
+use std::collections::BTreeMap;
 use std::sync::{Mutex, OnceLock};
 
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager};
 
+mod backend;
 mod commands;
 mod constants;
+mod disasm;
+mod features;
+#[cfg(test)]
+mod fixtures;
+mod freeze;
+mod pe;
+mod pointer;
 mod process;
+mod profile;
+mod scanner;
 mod settings;
+mod signature;
 mod state;
+mod telemetry;
+mod value;
+mod viewer;
+mod watcher;
+mod waypoints;
 mod window;
 
 pub(super) static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
@@ -14,11 +31,12 @@ pub(super) static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
 pub fn run() {
   tauri::Builder::default()
     .manage(state::ProcessState {
-      inner: Mutex::new(None),
+      sessions: Mutex::new(BTreeMap::new()),
     })
     .setup(|app| {
       let _ = APP_HANDLE.set(app.handle().clone());
       window::spawn_mouse_hook();
+      watcher::spawn_auto_attach(app.handle().clone());
       if cfg!(debug_assertions) {
         app.handle().plugin(
           tauri_plugin_log::Builder::default()
@@ -31,22 +49,50 @@ pub fn run() {
     .plugin(tauri_plugin_global_shortcut::Builder::new().build())
     .plugin(tauri_plugin_shell::init())
     .invoke_handler(tauri::generate_handler![
-      commands::apply_patch,
-      commands::apply_nop,
-      commands::apply_float,
+      commands::list_features,
+      commands::set_feature,
+      commands::read_memory,
+      commands::read_value,
+      commands::write_value,
+      commands::freeze_value,
+      commands::unfreeze_value,
+      commands::list_frozen,
+      commands::subscribe_telemetry,
+      commands::unsubscribe_telemetry,
+      commands::list_regions,
+      commands::list_game_processes,
       commands::attach_process,
       commands::detach_process,
       commands::status,
-      commands::set_invincibility,
-      commands::set_run_speed,
-      commands::reset_run_speed,
       settings::load_settings,
       settings::save_settings,
+      viewer::read_page,
+      disasm::disassemble_at,
+      scanner::first_scan,
+      scanner::next_scan,
+      scanner::scan_results,
+      scanner::promote_scan_result,
+      scanner::close_scan,
+      waypoints::list_waypoints,
+      waypoints::save_waypoint,
+      waypoints::restore_waypoint,
+      waypoints::delete_waypoint,
       commands::get_foreground_window_class,
       commands::get_offsets,
+      commands::get_module_info,
       commands::close_game,
       commands::launch_game
     ])
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .on_window_event(|window, event| {
+      if let tauri::WindowEvent::CloseRequested { .. } = event {
+        commands::release_on_exit(window.app_handle());
+      }
+    })
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(|app, event| {
+      if let tauri::RunEvent::ExitRequested { .. } = event {
+        commands::release_on_exit(app);
+      }
+    });
 }
@@ -0,0 +1,265 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::backend::MemoryBackend;
+
+const IMAGE_SCN_CNT_CODE: u32 = 0x0000_0020;
+const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x0000_0040;
+const IMAGE_SCN_MEM_DISCARDABLE: u32 = 0x0200_0000;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
+const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
+
+const IMAGE_FILE_MACHINE_I386: u16 = 0x014C;
+
+const SECTION_HEADER_SIZE: usize = 40;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PeSection {
+  pub(crate) name: String,
+  pub(crate) virtual_address: u32,
+  pub(crate) virtual_size: u32,
+  pub(crate) raw_offset: u32,
+  pub(crate) raw_size: u32,
+  pub(crate) characteristics: u32,
+  pub(crate) readable: bool,
+  pub(crate) writable: bool,
+  pub(crate) executable: bool,
+}
+
+impl PeSection {
+  /// RVA one past the end of the section once mapped.
+  pub(crate) fn end(&self) -> usize {
+    let size = if self.virtual_size == 0 { self.raw_size } else { self.virtual_size };
+    self.virtual_address as usize + size as usize
+  }
+
+  pub(crate) fn contains(&self, rva: usize, len: usize) -> bool {
+    rva >= self.virtual_address as usize
+      && rva.checked_add(len).is_some_and(|end| end <= self.end())
+  }
+
+  /// Code and initialised data (.text, .rdata, .data), i.e. what signatures
+  /// can match against. Discardable sections such as .reloc are skipped.
+  pub(crate) fn is_scannable(&self) -> bool {
+    self.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_CNT_INITIALIZED_DATA) != 0
+      && self.characteristics & IMAGE_SCN_MEM_DISCARDABLE == 0
+  }
+}
+
+/// Layout of a PE image, parsed either from a loaded module or from disk.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PeImage {
+  pub(crate) machine: u16,
+  pub(crate) timestamp: u32,
+  pub(crate) image_base: u64,
+  pub(crate) image_size: u32,
+  pub(crate) headers_size: u32,
+  pub(crate) entry_point: u32,
+  pub(crate) sections: Vec<PeSection>,
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Result<u16, String> {
+  bytes
+    .get(offset..offset + 2)
+    .map(|raw| u16::from_le_bytes([raw[0], raw[1]]))
+    .ok_or_else(|| "Truncated PE header".to_string())
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32, String> {
+  bytes
+    .get(offset..offset + 4)
+    .map(|raw| u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]))
+    .ok_or_else(|| "Truncated PE header".to_string())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Result<u64, String> {
+  Ok(u32_at(bytes, offset)? as u64 | (u32_at(bytes, offset + 4)? as u64) << 32)
+}
+
+impl PeImage {
+  /// Parses the headers through `read(offset, len)`, where offsets are
+  /// relative to the start of the image. Headers sit at the same offset in a
+  /// mapped module and in the file, so one parser serves both.
+  fn parse(mut read: impl FnMut(usize, usize) -> Result<Vec<u8>, String>) -> Result<Self, String> {
+    let dos = read(0, 0x40)?;
+    if dos.get(..2) != Some(b"MZ".as_slice()) {
+      return Err("Missing DOS header".to_string());
+    }
+    let nt_offset = u32_at(&dos, 0x3C)? as usize;
+
+    let file_header = read(nt_offset, 24)?;
+    if file_header.get(..4) != Some(b"PE\0\0".as_slice()) {
+      return Err("Missing NT header".to_string());
+    }
+    let machine = u16_at(&file_header, 4)?;
+    let section_count = u16_at(&file_header, 6)? as usize;
+    let timestamp = u32_at(&file_header, 8)?;
+    let optional_size = u16_at(&file_header, 20)? as usize;
+
+    let optional = read(nt_offset + 24, optional_size)?;
+    let image_base = match u16_at(&optional, 0)? {
+      0x20B => u64_at(&optional, 24)?,
+      0x10B => u32_at(&optional, 28)? as u64,
+      magic => return Err(format!("Unknown optional header magic {magic:#x}")),
+    };
+    let entry_point = u32_at(&optional, 16)?;
+    let image_size = u32_at(&optional, 56)?;
+    let headers_size = u32_at(&optional, 60)?;
+
+    let table = read(nt_offset + 24 + optional_size, section_count * SECTION_HEADER_SIZE)?;
+    let sections = table
+      .chunks_exact(SECTION_HEADER_SIZE)
+      .map(|header| {
+        let name_len = header[..8].iter().position(|byte| *byte == 0).unwrap_or(8);
+        let characteristics = u32_at(header, 36)?;
+        Ok(PeSection {
+          name: String::from_utf8_lossy(&header[..name_len]).into_owned(),
+          virtual_size: u32_at(header, 8)?,
+          virtual_address: u32_at(header, 12)?,
+          raw_size: u32_at(header, 16)?,
+          raw_offset: u32_at(header, 20)?,
+          characteristics,
+          readable: characteristics & IMAGE_SCN_MEM_READ != 0,
+          writable: characteristics & IMAGE_SCN_MEM_WRITE != 0,
+          executable: characteristics & IMAGE_SCN_MEM_EXECUTE != 0,
+        })
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(Self {
+      machine,
+      timestamp,
+      image_base,
+      image_size,
+      headers_size,
+      entry_point,
+      sections,
+    })
+  }
+
+  pub(crate) fn from_remote(backend: &dyn MemoryBackend, base: usize) -> Result<Self, String> {
+    Self::parse(|offset, len| backend.read(base + offset, len))
+  }
+
+  /// Parses an image on disk, e.g. a fixture or the executable behind a module
+  /// whose headers cannot be read from memory.
+  pub(crate) fn from_file(path: &Path) -> Result<Self, String> {
+    let mut file = File::open(path).map_err(|_| format!("Failed to open {}", path.display()))?;
+    Self::parse(|offset, len| {
+      let mut buffer = vec![0u8; len];
+      file
+        .seek(SeekFrom::Start(offset as u64))
+        .and_then(|_| file.read_exact(&mut buffer))
+        .map_err(|_| "Truncated PE file".to_string())?;
+      Ok(buffer)
+    })
+  }
+
+  /// Pointer width of the image: 4 for i386 images, 8 otherwise.
+  pub(crate) fn pointer_size(&self) -> usize {
+    if self.machine == IMAGE_FILE_MACHINE_I386 { 4 } else { 8 }
+  }
+
+  pub(crate) fn section_containing(&self, rva: usize, len: usize) -> Option<&PeSection> {
+    self.sections.iter().find(|section| section.contains(rva, len))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::backend::fake::FakeProcess;
+  use crate::app::fixtures::TINY_EXE;
+
+  fn fixture_path() -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/tiny.exe")
+  }
+
+  #[test]
+  fn parses_headers_from_file() {
+    let image = PeImage::from_file(&fixture_path()).unwrap();
+    assert_eq!(image.machine, 0x8664);
+    assert_eq!(image.timestamp, 0x6650_0000);
+    assert_eq!(image.image_base, 0x1_4000_0000);
+    assert_eq!(image.image_size, 0xA00);
+    assert_eq!(image.headers_size, 0x200);
+    assert_eq!(image.entry_point, 0x200);
+    assert_eq!(image.pointer_size(), 8);
+  }
+
+  #[test]
+  fn parses_sections() {
+    let image = PeImage::from_file(&fixture_path()).unwrap();
+    let names: Vec<_> = image.sections.iter().map(|section| section.name.as_str()).collect();
+    assert_eq!(names, [".text", ".rdata", ".data", ".reloc"]);
+
+    let text = &image.sections[0];
+    assert_eq!((text.virtual_address, text.virtual_size), (0x200, 0x200));
+    assert_eq!((text.raw_offset, text.raw_size), (0x200, 0x200));
+    assert!(text.readable && text.executable && !text.writable);
+    let data = &image.sections[2];
+    assert!(data.readable && data.writable && !data.executable);
+  }
+
+  #[test]
+  fn remote_and_file_agree() {
+    let process = FakeProcess::new(1).with_module("Sky.exe", 0x1_4000_0000, TINY_EXE.to_vec());
+    let remote = PeImage::from_remote(&process, 0x1_4000_0000).unwrap();
+    let file = PeImage::from_file(&fixture_path()).unwrap();
+    assert_eq!(remote.timestamp, file.timestamp);
+    assert_eq!(remote.sections.len(), file.sections.len());
+  }
+
+  #[test]
+  fn section_end_falls_back_to_raw_size() {
+    let image = PeImage::from_file(&fixture_path()).unwrap();
+    assert_eq!(image.sections[1].end(), 0x500);
+    // `.data` has no virtual size, so its raw size is what gets mapped.
+    assert_eq!(image.sections[2].end(), 0x800);
+  }
+
+  #[test]
+  fn section_containing_respects_bounds() {
+    let image = PeImage::from_file(&fixture_path()).unwrap();
+    let name = |rva, len| image.section_containing(rva, len).map(|section| section.name.as_str());
+    assert_eq!(name(0x200, 1), Some(".text"));
+    assert_eq!(name(0x3FC, 4), Some(".text"));
+    assert_eq!(name(0x3FD, 4), None, "range straddles .text and .rdata");
+    assert_eq!(name(0x4FF, 1), Some(".rdata"));
+    assert_eq!(name(0x500, 1), None, ".rdata ends at its virtual size");
+    assert_eq!(name(0x7FF, 1), Some(".data"));
+    assert_eq!(name(0x100, 4), None, "headers are outside every section");
+    assert_eq!(name(0xA00, 1), None);
+    assert_eq!(name(usize::MAX, 2), None, "range end overflows");
+    assert_eq!(name(0x200, usize::MAX), None, "length overflows");
+  }
+
+  #[test]
+  fn discardable_sections_are_not_scannable() {
+    let image = PeImage::from_file(&fixture_path()).unwrap();
+    let scannable: Vec<_> = image
+      .sections
+      .iter()
+      .filter(|section| section.is_scannable())
+      .map(|section| section.name.as_str())
+      .collect();
+    assert_eq!(scannable, [".text", ".rdata", ".data"]);
+  }
+
+  #[test]
+  fn rejects_files_that_are_not_images() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    assert_eq!(PeImage::from_file(&manifest).err().unwrap(), "Missing DOS header");
+
+    let mut truncated = TINY_EXE[..0x60].to_vec();
+    truncated.resize(0x80, 0);
+    let process = FakeProcess::new(1).with_region(0x1000, truncated);
+    assert!(PeImage::from_remote(&process, 0x1000).is_err());
+  }
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::profile::{deserialize_hex, serialize_hex};
+use super::state::ProcessHandle;
+
+/// Multi-level pointer, Cheat Engine style: `[[module + base] + o1] + o2`.
+/// Every offset is added to a dereferenced pointer; the final address itself
+/// is not read.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PointerPath {
+  /// Module the base offset is relative to; defaults to Sky.exe.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub(crate) module: Option<String>,
+  #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
+  pub(crate) base: usize,
+  #[serde(default, deserialize_with = "deserialize_offsets")]
+  pub(crate) offsets: Vec<i64>,
+}
+
+fn deserialize_offsets<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i64>, D::Error> {
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Raw {
+    Number(i64),
+    Text(String),
+  }
+  Vec::<Raw>::deserialize(deserializer)?
+    .into_iter()
+    .map(|raw| match raw {
+      Raw::Number(value) => Ok(value),
+      Raw::Text(text) => {
+        let (negative, magnitude) = match text.strip_prefix('-') {
+          Some(rest) => (true, rest),
+          None => (false, text.as_str()),
+        };
+        let digits = magnitude.trim_start_matches("0x").trim_start_matches("0X");
+        let value = i64::from_str_radix(digits, 16)
+          .map_err(|_| serde::de::Error::custom(format!("invalid hex offset `{text}`")))?;
+        Ok(if negative { -value } else { value })
+      }
+    })
+    .collect()
+}
+
+/// Where a command writes or reads: an RVA into Sky.exe, as before, a
+/// pointer path that is followed every time the target is used, or an
+/// absolute address such as a heap value found by the scanner, written
+/// `{ "address": "0x1F2A3B4C5D0" }`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum Target {
+  Offset(u64),
+  Pointer(PointerPath),
+  Address(#[serde(with = "address")] usize),
+}
+
+/// `{ "address": "0x..." }`, so an address is not mistaken for an RVA.
+mod address {
+  use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+  use crate::app::profile::{deserialize_hex, serialize_hex};
+
+  #[derive(Serialize, Deserialize)]
+  #[serde(deny_unknown_fields)]
+  struct Address {
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
+    address: usize,
+  }
+
+  pub(super) fn serialize<S: Serializer>(
+    address: &usize,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    Address { address: *address }.serialize(serializer)
+  }
+
+  pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<usize, D::Error> {
+    Address::deserialize(deserializer).map(|wrapper| wrapper.address)
+  }
+}
+
+impl PointerPath {
+  /// Follows the path in the game's memory. Fails on a null or unreadable
+  /// pointer, naming the level that broke so stale paths are easy to spot.
+  pub(crate) fn resolve(&self, handle: &ProcessHandle) -> Result<usize, String> {
+    let module_base = match &self.module {
+      Some(name) => {
+        handle
+          .backend
+          .module(name)
+          .ok_or_else(|| format!("Module {name} is not loaded"))?
+          .base
+      }
+      None => handle.base,
+    };
+    let width = handle.image.as_ref().map_or(8, |image| image.pointer_size());
+    let mut address = module_base
+      .checked_add(self.base)
+      .ok_or_else(|| format!("Pointer base {:#x} overflows", self.base))?;
+    for (level, offset) in self.offsets.iter().enumerate() {
+      let raw = handle
+        .backend
+        .read(address, width)
+        .map_err(|_| format!("Pointer level {level}: {address:#x} is not readable"))?;
+      let mut value = [0u8; 8];
+      value[..width].copy_from_slice(&raw);
+      let pointer = u64::from_le_bytes(value) as usize;
+      if pointer == 0 {
+        return Err(format!("Pointer level {level}: null pointer at {address:#x}"));
+      }
+      address = pointer
+        .checked_add_signed(*offset as isize)
+        .ok_or_else(|| format!("Pointer level {level}: address overflows"))?;
+    }
+    Ok(address)
+  }
+}
+
+impl ProcessHandle {
+  /// Absolute address of `target` for an access of `len` bytes. Offsets must
+  /// fall inside a section of Sky.exe; pointer paths usually land on the heap
+  /// and are only checked for being readable along the way. An absolute
+  /// address must not wrap, and if it touches Sky.exe it is held to the same
+  /// sections as an offset.
+  pub(crate) fn resolve_target(&self, target: &Target, len: usize) -> Result<usize, String> {
+    match target {
+      Target::Offset(offset) => {
+        let address = usize::try_from(*offset)
+          .ok()
+          .and_then(|offset| self.base.checked_add(offset))
+          .ok_or_else(|| format!("Offset {offset:#x} is outside the address space"))?;
+        self.check_range(address - self.base, len)?;
+        Ok(address)
+      }
+      Target::Pointer(path) => path.resolve(self),
+      Target::Address(address) => {
+        let end = address
+          .checked_add(len)
+          .ok_or_else(|| format!("Address {address:#x} (+{len}) overflows"))?;
+        let module_end = self.image.as_ref().map_or(self.base, |image| {
+          self.base.saturating_add(image.image_size as usize)
+        });
+        if *address < module_end && end > self.base {
+          let offset = address.checked_sub(self.base).ok_or_else(|| {
+            format!("Address {address:#x} (+{len}) straddles the start of Sky.exe")
+          })?;
+          self.check_range(offset, len)?;
+        }
+        Ok(*address)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::backend::fake::{FakeProcess, SKY_BASE};
+
+  #[test]
+  fn resolves_offsets_inside_sections() {
+    let handle = FakeProcess::sky(1).attach(&[]);
+    assert_eq!(handle.resolve_target(&Target::Offset(0x600), 4), Ok(SKY_BASE + 0x600));
+    assert!(handle.resolve_target(&Target::Offset(0x7FE), 4).is_err());
+  }
+
+  #[test]
+  fn rejects_offsets_that_overflow() {
+    let mut handle = FakeProcess::sky(1).attach(&[]);
+    for offset in [u64::MAX, u64::MAX - SKY_BASE as u64 + 1] {
+      assert!(handle.resolve_target(&Target::Offset(offset), 1).is_err(), "{offset:#x}");
+    }
+    // Without a parsed image the range check cannot catch it.
+    handle.image = None;
+    let error = handle.resolve_target(&Target::Offset(u64::MAX), 1).unwrap_err();
+    assert!(error.contains("outside the address space"), "{error}");
+    let path = PointerPath {
+      module: None,
+      base: usize::MAX,
+      offsets: Vec::new(),
+    };
+    assert!(handle.resolve_target(&Target::Pointer(path), 1).unwrap_err().contains("overflows"));
+  }
+
+  #[test]
+  fn checks_absolute_addresses() {
+    let process = FakeProcess::sky(1).with_region(0x5000_0000, vec![0; 0x100]);
+    let handle = process.attach(&[]);
+    let at = |address| handle.resolve_target(&Target::Address(address), 4);
+    assert_eq!(at(0x5000_0010), Ok(0x5000_0010));
+    assert_eq!(at(SKY_BASE + 0x600), Ok(SKY_BASE + 0x600));
+    assert!(at(SKY_BASE + 0x100).unwrap_err().contains("outside Sky.exe's sections"));
+    assert!(at(SKY_BASE - 2).unwrap_err().contains("straddles"));
+    assert!(at(usize::MAX - 1).unwrap_err().contains("overflows"));
+  }
+
+  #[test]
+  fn parses_every_target_shape() {
+    let parse = |json| serde_json::from_str::<Target>(json).unwrap();
+    assert!(matches!(parse("4096"), Target::Offset(0x1000)));
+    assert!(matches!(parse(r#"{ "base": "0x10", "offsets": [8] }"#), Target::Pointer(_)));
+    assert!(matches!(parse(r#"{ "address": "0x5000" }"#), Target::Address(0x5000)));
+    let json = serde_json::to_string(&Target::Address(0x5000)).unwrap();
+    assert_eq!(json, r#"{"address":"0x5000"}"#);
+  }
+
+  #[test]
+  fn follows_pointer_paths() {
+    let handle = FakeProcess::sky(1).attach(&[]);
+    // .data holds a pointer to .rdata at 0x604.
+    let path = PointerPath {
+      module: None,
+      base: 0x604,
+      offsets: vec![4],
+    };
+    assert_eq!(handle.resolve_target(&Target::Pointer(path), 4), Ok(SKY_BASE + 0x404));
+    let null = PointerPath {
+      module: None,
+      base: 0x60C,
+      offsets: vec![0],
+    };
+    let error = handle.resolve_target(&Target::Pointer(null), 4).unwrap_err();
+    assert!(error.contains("null pointer"), "{error}");
+  }
+}
@@ -1,239 +1,307 @@
-use windows_sys::Win32::Foundation::{CloseHandle, BOOL, HWND};
-use windows_sys::Win32::System::Diagnostics::Debug::{
-  FlushInstructionCache, ReadProcessMemory, WriteProcessMemory,
-};
-use windows_sys::Win32::System::Diagnostics::ToolHelp::{
-  CreateToolhelp32Snapshot, Module32FirstW, Module32NextW, Process32FirstW, Process32NextW,
-  Thread32First, Thread32Next, MODULEENTRY32W, PROCESSENTRY32W, THREADENTRY32, TH32CS_SNAPMODULE,
-  TH32CS_SNAPMODULE32, TH32CS_SNAPPROCESS, TH32CS_SNAPTHREAD,
-};
-use windows_sys::Win32::System::Memory::{VirtualProtectEx};
-use windows_sys::Win32::System::Threading::OpenThread;
-use windows_sys::Win32::System::Threading::{ResumeThread, SuspendThread, THREAD_SUSPEND_RESUME};
-
-pub(crate) fn read_wide(buf: &[u16]) -> String {
-  let len = buf.iter().position(|c| *c == 0).unwrap_or(buf.len());
-  String::from_utf16_lossy(&buf[..len])
-}
+use std::collections::{BTreeSet, HashMap};
 
-pub(crate) fn find_pid_by_window_class(class_name: &str) -> Option<u32> {
-  struct Search {
-    class_name: String,
-    pid: u32,
-  }
+use super::backend::{MemoryBackend, ModuleInfo};
+use super::disasm;
+use super::features::FeatureDef;
+use super::pe::PeImage;
+use super::profile::{OffsetProfile, SiteLocator};
+use super::signature::scan_ranges;
+use super::state::{
+  DetachResponse, FeatureError, FeatureOperation, PatchRecord, ProcessHandle, RestoreFailure,
+};
 
-  unsafe extern "system" fn enum_proc(hwnd: HWND, lparam: isize) -> BOOL {
-    let search = &mut *(lparam as *mut Search);
-    let mut buffer = [0u16; 256];
-    let len = windows_sys::Win32::UI::WindowsAndMessaging::GetClassNameW(
-      hwnd,
-      buffer.as_mut_ptr(),
-      buffer.len() as i32,
-    );
-    if len > 0 {
-      let current = read_wide(&buffer);
-      if current == search.class_name {
-        let mut pid = 0u32;
-        windows_sys::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId(hwnd, &mut pid);
-        if pid != 0 {
-          search.pid = pid;
-          return 0;
-        }
-      }
+/// Resolves every site of `profile` to an absolute address in `module`.
+/// Signatures are only matched inside the image's code and data sections.
+pub(crate) fn resolve_sites(
+  backend: &dyn MemoryBackend,
+  module: &ModuleInfo,
+  image: &PeImage,
+  profile: &OffsetProfile,
+) -> HashMap<String, Result<usize, String>> {
+  let signatures: Vec<_> = profile
+    .sites
+    .iter()
+    .filter_map(|(name, locator)| match locator {
+      SiteLocator::Signature(signature) => Some((name.as_str(), signature)),
+      SiteLocator::Offset(_) => None,
+    })
+    .collect();
+  let ranges: Vec<_> = image
+    .sections
+    .iter()
+    .filter(|section| section.is_scannable())
+    .map(|section| {
+      let start = module.base + section.virtual_address as usize;
+      (start, module.base + section.end())
+    })
+    .collect();
+  let mut sites = scan_ranges(backend, &ranges, &signatures);
+  for (name, locator) in &profile.sites {
+    if let SiteLocator::Offset(offset) = locator {
+      sites.insert(name.clone(), Ok(module.base + offset));
     }
-    1
   }
-
-  let mut search = Box::new(Search {
-    class_name: class_name.to_string(),
-    pid: 0,
-  });
-  let ptr = &mut *search as *mut Search as isize;
-  unsafe {
-    windows_sys::Win32::UI::WindowsAndMessaging::EnumWindows(Some(enum_proc), ptr);
-  }
-  if search.pid == 0 { None } else { Some(search.pid) }
+  sites
 }
 
-pub(crate) fn find_pid_by_name(process_name: &str) -> Option<u32> {
-  unsafe {
-    let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
-    if snapshot == 0 || snapshot == -1 {
-      return None;
-    }
-    let mut entry = PROCESSENTRY32W {
-      dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
-      ..std::mem::zeroed()
-    };
-    if Process32FirstW(snapshot, &mut entry) == 0 {
-      CloseHandle(snapshot);
-      return None;
-    }
-    let target = process_name.to_ascii_lowercase();
-    loop {
-      let name = read_wide(&entry.szExeFile).to_ascii_lowercase();
-      if name == target {
-        CloseHandle(snapshot);
-        return Some(entry.th32ProcessID);
+/// Takes or releases `owner`'s reference to the patch at `address`. A range
+/// already patched with the same bytes is shared instead of written again;
+/// one that overlaps a different patch is refused, since restoring either
+/// would corrupt the other. The original is written back only when the last
+/// owner releases the range.
+pub(crate) fn apply_patch_at(
+  handle: &mut ProcessHandle,
+  owner: &str,
+  address: usize,
+  bytes: &[u8],
+  enabled: bool,
+) -> Result<(), String> {
+  if !enabled {
+    match handle.patches.get_mut(&address) {
+      Some(record) if record.owners.iter().all(|id| id == owner) => {
+        handle.backend.write(address, &record.original)?;
+        handle.patches.remove(&address);
       }
-      if Process32NextW(snapshot, &mut entry) == 0 {
-        break;
+      Some(record) => {
+        record.owners.remove(owner);
       }
+      None => {}
     }
-    CloseHandle(snapshot);
+    return Ok(());
   }
-  None
-}
 
-pub(crate) fn get_module_base(pid: u32, module_name: &str) -> Option<usize> {
-  unsafe {
-    let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32, pid);
-    if snapshot == 0 || snapshot == -1 {
-      return None;
-    }
-    let mut entry = MODULEENTRY32W {
-      dwSize: std::mem::size_of::<MODULEENTRY32W>() as u32,
-      ..std::mem::zeroed()
-    };
-    if Module32FirstW(snapshot, &mut entry) == 0 {
-      CloseHandle(snapshot);
-      return None;
+  let existing = handle
+    .patches
+    .iter_mut()
+    .find(|(start, record)| record.overlaps(**start, address, bytes.len()));
+  match existing {
+    None => {
+      let original = handle.backend.read(address, bytes.len())?;
+      handle.backend.write(address, bytes)?;
+      let record = PatchRecord {
+        original,
+        patched: bytes.to_vec(),
+        owners: BTreeSet::from([owner.to_string()]),
+      };
+      handle.patches.insert(address, record);
     }
-    let target = module_name.to_ascii_lowercase();
-    loop {
-      let name = read_wide(&entry.szModule).to_ascii_lowercase();
-      if name == target {
-        CloseHandle(snapshot);
-        return Some(entry.modBaseAddr as usize);
-      }
-      if Module32NextW(snapshot, &mut entry) == 0 {
-        break;
+    Some((start, record)) if *start == address && record.original.len() == bytes.len() => {
+      if record.patched != bytes {
+        if record.owners.iter().any(|id| id != owner) {
+          return Err(format!("{address:#x} is already patched differently by {}", owners(record)));
+        }
+        handle.backend.write(address, bytes)?;
+        record.patched = bytes.to_vec();
       }
+      record.owners.insert(owner.to_string());
+    }
+    Some((start, record)) => {
+      return Err(format!(
+        "overlaps the patch at {start:#x} (+{}) held by {}",
+        record.original.len(),
+        owners(record)
+      ));
     }
-    CloseHandle(snapshot);
   }
-  None
+  Ok(())
 }
 
-pub(crate) fn read_bytes(handle: isize, address: usize, size: usize) -> Result<Vec<u8>, String> {
-  let mut buffer = vec![0u8; size];
-  let mut read = 0usize;
-  let ok = unsafe {
-    ReadProcessMemory(
-      handle,
-      address as *const _,
-      buffer.as_mut_ptr() as *mut _,
-      size,
-      &mut read,
-    )
-  };
-  if ok == 0 || read != size {
-    return Err("Failed to read process memory".to_string());
-  }
-  Ok(buffer)
+fn owners(record: &PatchRecord) -> String {
+  record.owners.iter().cloned().collect::<Vec<_>>().join(", ")
 }
 
-pub(crate) fn write_bytes(handle: isize, address: usize, bytes: &[u8]) -> Result<(), String> {
-  let mut old_protect = 0u32;
-  let protect_ok = unsafe {
-    VirtualProtectEx(
-      handle,
-      address as *mut _,
-      bytes.len(),
-      windows_sys::Win32::System::Memory::PAGE_EXECUTE_READWRITE,
-      &mut old_protect,
-    )
-  };
-  if protect_ok == 0 {
-    return Err("Failed to change memory protection".to_string());
+/// Writes every recorded original back under one suspension, or with
+/// `keep_patches` leaves them in the game. Sites that could not be restored
+/// are reported rather than silently forgotten.
+pub(crate) fn release(handle: &mut ProcessHandle, keep_patches: bool) -> DetachResponse {
+  let mut response = DetachResponse::default();
+  if keep_patches {
+    response.kept = handle.patches.len();
+    handle.patches.clear();
+    return response;
   }
-
-  let mut written = 0usize;
-  let ok = unsafe {
-    WriteProcessMemory(
-      handle,
-      address as *mut _,
-      bytes.as_ptr() as *const _,
-      bytes.len(),
-      &mut written,
-    )
-  };
-
-  unsafe {
-    FlushInstructionCache(handle, address as *const _, bytes.len());
-    let mut _restore = 0u32;
-    VirtualProtectEx(
-      handle,
-      address as *mut _,
-      bytes.len(),
-      old_protect,
-      &mut _restore,
-    );
+  let threads = handle.backend.suspend_threads();
+  for (address, record) in std::mem::take(&mut handle.patches) {
+    match handle.backend.write(address, &record.original) {
+      Ok(()) => response.restored += 1,
+      Err(error) => response.failed.push(RestoreFailure {
+        offset: address.wrapping_sub(handle.base),
+        error,
+      }),
+    }
   }
+  handle.backend.resume_threads(threads);
+  response
+}
 
-  if ok == 0 || written != bytes.len() {
-    return Err("Failed to write process memory".to_string());
+/// Accepts the live bytes if they match the expected pattern. Code that
+/// already equals the patch (e.g. kept by an earlier session) is refused: the
+/// record would save it as the original, and the feature could then never be
+/// turned off. Data may hold the patched value naturally, such as a flag that
+/// is already set, so it is only checked against the pattern.
+fn verify_site(operation: &FeatureOperation, live: &[u8], code: bool) -> Result<(), String> {
+  let expected = &operation.expected;
+  if code && live == operation.bytes.as_slice() {
+    return Err("site is already patched; restart the game to restore it".to_string());
   }
-  Ok(())
+  if expected.len() == live.len() && expected.matches(live) {
+    return Ok(());
+  }
+  let found: Vec<_> = live.iter().map(|byte| format!("{byte:02X}")).collect();
+  Err(format!("site mismatch, found {}, expected {expected}", found.join(" ")))
 }
 
-pub(crate) fn apply_patch_at(
-  handle: &mut crate::app::state::ProcessHandle,
+/// State of one address before a feature operation touched it.
+struct Undo {
+  index: usize,
   address: usize,
-  bytes: &[u8],
+  bytes: Vec<u8>,
+  record: Option<PatchRecord>,
+}
+
+/// Enables or disables every operation of `feature` under one suspension.
+/// Before a site is first patched its live bytes are checked against the
+/// expected pattern, so a stale profile never overwrites unrelated code. If
+/// any operation fails, the ones already written are put back exactly as they
+/// were, including their patch records, so the game is never left with half a
+/// feature applied.
+pub(crate) fn apply_feature(
+  handle: &mut ProcessHandle,
+  feature: &FeatureDef,
+  operations: &[FeatureOperation],
   enabled: bool,
-) -> Result<(), String> {
-  if enabled {
-    if !handle.original.contains_key(&address) {
-      let original = read_bytes(handle.handle, address, bytes.len())?;
-      handle.original.insert(address, original);
+) -> Result<(), FeatureError> {
+  let threads = handle.backend.suspend_threads();
+  let mut undo = Vec::new();
+  let mut failure = None;
+  for (index, operation) in operations.iter().enumerate() {
+    let bytes = &operation.bytes;
+    let step = handle
+      .resolve_target(&operation.target, bytes.len())
+      .and_then(|address| {
+        let before = handle.backend.read(address, bytes.len())?;
+        if enabled && !handle.patches.contains_key(&address) {
+          verify_site(operation, &before, disasm::in_code(handle, address, bytes.len()))?;
+        }
+        let record = handle.patches.get(&address).cloned();
+        apply_patch_at(handle, feature.id, address, bytes, enabled)?;
+        undo.push(Undo {
+          index,
+          address,
+          bytes: before,
+          record,
+        });
+        Ok(())
+      });
+    if let Err(error) = step {
+      failure = Some((index, format!("{} ({}): {error}", feature.label, operation.site)));
+      break;
     }
-    write_bytes(handle.handle, address, bytes)?;
-    return Ok(());
-  }
-  if let Some(original) = handle.original.remove(&address) {
-    write_bytes(handle.handle, address, &original)?;
   }
-  Ok(())
-}
 
-pub(crate) fn suspend_process_threads(pid: u32) -> Vec<isize> {
-  let mut handles = Vec::new();
-  unsafe {
-    let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
-    if snapshot == 0 || snapshot == -1 {
-      return handles;
-    }
-    let mut entry = THREADENTRY32 {
-      dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
-      ..std::mem::zeroed()
-    };
-    if Thread32First(snapshot, &mut entry) == 0 {
-      CloseHandle(snapshot);
-      return handles;
-    }
-    loop {
-      if entry.th32OwnerProcessID == pid {
-        let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
-        if thread != 0 {
-          SuspendThread(thread);
-          handles.push(thread);
+  let result = match failure {
+    None => Ok(()),
+    Some((index, error)) => {
+      let mut unreverted = Vec::new();
+      for step in undo.into_iter().rev() {
+        if handle.backend.write(step.address, &step.bytes).is_err() {
+          unreverted.push(step.index);
+          continue;
         }
+        match step.record {
+          Some(record) => handle.patches.insert(step.address, record),
+          None => handle.patches.remove(&step.address),
+        };
       }
-      if Thread32Next(snapshot, &mut entry) == 0 {
-        break;
-      }
+      unreverted.reverse();
+      Err(FeatureError {
+        operation: Some(index),
+        message: error,
+        unreverted,
+      })
     }
-    CloseHandle(snapshot);
-  }
-  handles
+  };
+  handle.backend.resume_threads(threads);
+  result
 }
 
-pub(crate) fn resume_process_threads(handles: Vec<isize>) {
-  for handle in handles {
-    unsafe {
-      ResumeThread(handle);
-      CloseHandle(handle);
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::*;
+  use crate::app::backend::fake::{FakeProcess, SKY_BASE};
+  use crate::app::features::{self, find};
+  use crate::app::pointer::Target;
+  use crate::app::signature::Pattern;
+
+  fn operation(site: &str, rva: u64, bytes: &[u8], expected: &str) -> FeatureOperation {
+    FeatureOperation {
+      site: site.to_string(),
+      target: Target::Offset(rva),
+      bytes: bytes.to_vec(),
+      expected: Pattern::parse(expected).unwrap(),
     }
   }
+
+  #[test]
+  fn rolls_back_when_an_operation_fails() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[]);
+    let operations = [
+      operation("superRunPatch", 0x60A, &[0x01], "??"),
+      // Past the last section, so it fails after the first write.
+      operation("runSpeed", 0xA00, &[0; 4], "?? ?? ?? ??"),
+    ];
+    let feature = find("super-run").unwrap();
+    let error = apply_feature(&mut handle, feature, &operations, true).unwrap_err();
+    assert_eq!(error.operation, Some(1));
+    assert!(error.unreverted.is_empty());
+    assert_eq!(process.bytes(SKY_BASE + 0x60A, 1), [0x00]);
+    assert!(handle.patches.is_empty());
+    assert!(!process.is_suspended());
+  }
+
+  #[test]
+  fn rollback_restores_shared_records() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[("invincibility", 0x60A)]);
+    features::set_feature(&mut handle, "godmode", true, &HashMap::new()).unwrap();
+    let operations = [
+      operation("superRunPatch", 0x60A, &[0x01], "??"),
+      operation("runSpeed", 0xA00, &[0; 4], "?? ?? ?? ??"),
+    ];
+    apply_feature(&mut handle, find("super-run").unwrap(), &operations, true).unwrap_err();
+    let record = &handle.patches[&(SKY_BASE + 0x60A)];
+    assert_eq!(record.owners.iter().collect::<Vec<_>>(), ["godmode"]);
+    assert_eq!(process.bytes(SKY_BASE + 0x60A, 1), [0x01]);
+  }
+
+  #[test]
+  fn release_restores_every_patch() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[("invincibility", 0x60A), ("superFlight", 0x210)]);
+    features::set_feature(&mut handle, "godmode", true, &HashMap::new()).unwrap();
+    features::set_feature(&mut handle, "super-flight", true, &HashMap::new()).unwrap();
+
+    let response = release(&mut handle, false);
+    assert_eq!((response.restored, response.kept), (2, 0));
+    assert!(response.failed.is_empty());
+    assert_eq!(process.bytes(SKY_BASE + 0x60A, 1), [0x00]);
+    assert_eq!(process.bytes(SKY_BASE + 0x214, 2), [0x80, 0x3F]);
+    assert!(handle.patches.is_empty());
+    assert!(!process.is_suspended());
+  }
+
+  #[test]
+  fn release_can_keep_patches() {
+    let process = FakeProcess::sky(1);
+    let mut handle = process.attach(&[("invincibility", 0x60A)]);
+    features::set_feature(&mut handle, "godmode", true, &HashMap::new()).unwrap();
+
+    let response = release(&mut handle, true);
+    assert_eq!((response.restored, response.kept), (0, 1));
+    assert_eq!(process.bytes(SKY_BASE + 0x60A, 1), [0x01]);
+    assert!(handle.patches.is_empty());
+  }
 }
@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use super::pe::PeImage;
+use super::signature::Signature;
+use super::value::Probe;
+
+const BUNDLED_DEFAULT: &str = include_str!("../../profiles/default.json");
+
+/// Identity of the Sky.exe image that is loaded in the game.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BuildIdentity {
+  pub(crate) timestamp: u32,
+  pub(crate) image_size: u32,
+  #[serde(skip)]
+  pub(crate) path: String,
+}
+
+impl BuildIdentity {
+  pub(crate) fn new(image: &PeImage, path: &str) -> Self {
+    Self {
+      timestamp: image.timestamp,
+      image_size: image.image_size,
+      path: path.to_string(),
+    }
+  }
+}
+
+/// Which builds a profile applies to. Every field that is set must match; a
+/// profile without any field is a fallback for unknown builds.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct BuildMatch {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) timestamp: Option<u32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) image_size: Option<u32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) sha256: Option<String>,
+}
+
+impl BuildMatch {
+  fn is_fallback(&self) -> bool {
+    self.timestamp.is_none() && self.image_size.is_none() && self.sha256.is_none()
+  }
+
+  fn matches(&self, build: &BuildIdentity, file_hash: &mut impl FnMut() -> Option<String>) -> bool {
+    if self.timestamp.is_some_and(|timestamp| timestamp != build.timestamp) {
+      return false;
+    }
+    if self.image_size.is_some_and(|size| size != build.image_size) {
+      return false;
+    }
+    match &self.sha256 {
+      Some(expected) => file_hash().is_some_and(|hash| hash.eq_ignore_ascii_case(expected)),
+      None => true,
+    }
+  }
+}
+
+/// Where a feature site lives: a fixed RVA (`"0x2FF40E2"` or a number), or a
+/// signature that is scanned for at attach.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum SiteLocator {
+  Offset(
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")] usize,
+  ),
+  Signature(Signature),
+}
+
+pub(crate) fn serialize_hex<S: Serializer>(
+  value: &usize,
+  serializer: S,
+) -> Result<S::Ok, S::Error> {
+  serializer.serialize_str(&format!("0x{value:X}"))
+}
+
+pub(crate) fn deserialize_hex<'de, D: Deserializer<'de>>(
+  deserializer: D,
+) -> Result<usize, D::Error> {
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Raw {
+    Number(usize),
+    Text(String),
+  }
+  match Raw::deserialize(deserializer)? {
+    Raw::Number(value) => Ok(value),
+    Raw::Text(text) => {
+      let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+      usize::from_str_radix(digits, 16)
+        .map_err(|_| serde::de::Error::custom(format!("invalid hex offset `{text}`")))
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OffsetProfile {
+  pub(crate) name: String,
+  #[serde(default)]
+  pub(crate) build: BuildMatch,
+  pub(crate) default_run_speed: f32,
+  pub(crate) sites: BTreeMap<String, SiteLocator>,
+  /// Per-site override of the bytes a feature expects before patching, as a
+  /// masked pattern (see `features.rs`).
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub(crate) expected: BTreeMap<String, String>,
+  /// Named game values such as the player's position, read by telemetry and
+  /// waypoints.
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub(crate) values: BTreeMap<String, Probe>,
+}
+
+pub(crate) fn default_profile() -> OffsetProfile {
+  serde_json::from_str(BUNDLED_DEFAULT).expect("bundled offset profile is valid")
+}
+
+/// Profiles from `<config>/profiles/*.json`, followed by the bundled default.
+/// User files come first so they take precedence over the bundled profile,
+/// which is never a fallback: its RVAs only hold for the build it pins.
+pub(crate) fn load_profiles(app: &AppHandle) -> Vec<OffsetProfile> {
+  let mut profiles = Vec::new();
+  let entries = app
+    .path()
+    .app_config_dir()
+    .ok()
+    .and_then(|dir| fs::read_dir(dir.join("profiles")).ok());
+  if let Some(entries) = entries {
+    let mut paths: Vec<_> = entries
+      .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+      .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+      .collect();
+    paths.sort();
+    for path in paths {
+      let parsed = fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| serde_json::from_str(&contents).map_err(|err| err.to_string()));
+      match parsed {
+        Ok(profile) => profiles.push(profile),
+        Err(err) => log::warn!("Skipping offset profile {}: {err}", path.display()),
+      }
+    }
+  }
+  push_bundled(&mut profiles, default_profile());
+  profiles
+}
+
+/// Adds the bundled profile after the user's, unless it pins no build.
+fn push_bundled(profiles: &mut Vec<OffsetProfile>, bundled: OffsetProfile) {
+  if bundled.build.is_fallback() {
+    log::warn!("Bundled offset profile is not pinned to a build; ignoring it");
+  } else {
+    profiles.push(bundled);
+  }
+}
+
+/// Picks the first profile whose build criteria all match, or the first
+/// fallback profile. The file hash is only computed if a profile asks for it.
+pub(crate) fn select_profile(
+  profiles: Vec<OffsetProfile>,
+  build: &BuildIdentity,
+) -> Option<OffsetProfile> {
+  let mut cached_hash: Option<Option<String>> = None;
+  let mut file_hash = || cached_hash.get_or_insert_with(|| hash_file(&build.path)).clone();
+  let mut fallback = None;
+  for profile in profiles {
+    if profile.build.is_fallback() {
+      fallback.get_or_insert(profile);
+    } else if profile.build.matches(build, &mut file_hash) {
+      return Some(profile);
+    }
+  }
+  fallback
+}
+
+fn hash_file(path: &str) -> Option<String> {
+  let mut file = fs::File::open(path).ok()?;
+  let mut hasher = Sha256::new();
+  std::io::copy(&mut file, &mut hasher).ok()?;
+  Some(
+    hasher
+      .finalize()
+      .iter()
+      .map(|byte| format!("{byte:02x}"))
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  use super::*;
+
+  const TINY_EXE_SHA256: &str = "feec03e428995c22191059664b12ad01f841caf36925ba3de22181a2e5d489a9";
+
+  /// The fixture's identity, as `attach` would build it.
+  fn fixture_build() -> BuildIdentity {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/tiny.exe");
+    let image = PeImage::from_file(&path).unwrap();
+    BuildIdentity::new(&image, &path.to_string_lossy())
+  }
+
+  fn pinned_to(build: &BuildIdentity) -> BuildMatch {
+    BuildMatch {
+      timestamp: Some(build.timestamp),
+      image_size: Some(build.image_size),
+      sha256: None,
+    }
+  }
+
+  fn named(name: &str, build: BuildMatch) -> OffsetProfile {
+    OffsetProfile {
+      name: name.to_string(),
+      build,
+      ..default_profile()
+    }
+  }
+
+  /// What `load_profiles` returns with no user profiles and the bundled one
+  /// pinned to `build`.
+  fn bundled_pinned_to(build: BuildMatch) -> Vec<OffsetProfile> {
+    let mut profiles = Vec::new();
+    push_bundled(&mut profiles, named(&default_profile().name, build));
+    profiles
+  }
+
+  #[test]
+  fn selects_the_bundled_profile_for_its_build() {
+    let build = fixture_build();
+    let pin = BuildMatch {
+      sha256: Some(TINY_EXE_SHA256.to_uppercase()),
+      ..pinned_to(&build)
+    };
+    let selected = select_profile(bundled_pinned_to(pin.clone()), &build).unwrap();
+    assert_eq!(selected.name, default_profile().name);
+    assert_eq!(selected.sites.len(), default_profile().sites.len());
+
+    let mismatched = [
+      BuildMatch {
+        timestamp: Some(build.timestamp + 1),
+        ..pin.clone()
+      },
+      BuildMatch {
+        image_size: Some(build.image_size * 2),
+        ..pin.clone()
+      },
+      BuildMatch {
+        sha256: Some("00".repeat(32)),
+        ..pin
+      },
+    ];
+    for other in mismatched {
+      assert!(select_profile(bundled_pinned_to(other), &build).is_none());
+    }
+  }
+
+  #[test]
+  fn unpinned_bundled_profile_is_never_a_fallback() {
+    assert!(bundled_pinned_to(BuildMatch::default()).is_empty());
+  }
+
+  #[test]
+  fn user_profiles_take_precedence_and_fall_back() {
+    let build = fixture_build();
+    let mut profiles = vec![
+      named("Fallback", BuildMatch::default()),
+      named("Mine", pinned_to(&build)),
+    ];
+    push_bundled(&mut profiles, named("Bundled", pinned_to(&build)));
+    assert_eq!(select_profile(profiles, &build).unwrap().name, "Mine");
+
+    let stale = BuildMatch {
+      timestamp: Some(build.timestamp - 1),
+      ..BuildMatch::default()
+    };
+    let profiles = vec![named("Stale", stale), named("Fallback", BuildMatch::default())];
+    assert_eq!(select_profile(profiles, &build).unwrap().name, "Fallback");
+  }
+}
@@ -0,0 +1,649 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use super::backend::MemoryBackend;
+use super::pointer::{PointerPath, Target};
+use super::state::{find_session, find_session_mut, ProcessHandle, ProcessState, Sessions};
+use super::value::{Endian, Probe, Value, ValueType};
+
+/// Regions are read this much at a time.
+const CHUNK: usize = 1 << 20;
+const MAX_CANDIDATES: usize = 4_000_000;
+/// Unknown-value scans copy every writable region; beyond this the user has
+/// to start from an exact or range scan instead.
+const MAX_SNAPSHOT: usize = 512 << 20;
+const MAX_PAGE: usize = 1000;
+/// Floats found by an exact scan may differ from the shown value by rounding.
+const FLOAT_TOLERANCE: f64 = 1e-3;
+/// How far past a static pointer a promoted heap address may lie.
+const MAX_POINTER_OFFSET: usize = 0x1000;
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+#[derive(Deserialize)]
+#[serde(tag = "mode", rename_all = "camelCase")]
+pub(crate) enum FirstScan {
+  Exact { value: Value },
+  Range { min: f64, max: f64 },
+  Unknown,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "mode", rename_all = "camelCase")]
+pub(crate) enum NextScan {
+  Changed,
+  Unchanged,
+  Increased,
+  Decreased,
+  Equal { value: Value },
+}
+
+/// What candidates are compared against.
+enum Matcher {
+  Bytes(Vec<u8>),
+  Number { min: f64, max: f64 },
+}
+
+/// Results of a scan, kept on the session until closed or detached.
+pub(crate) struct Scan {
+  value_type: ValueType,
+  endian: Endian,
+  results: Results,
+  truncated: bool,
+}
+
+enum Results {
+  /// Unknown initial value: copies of every region, compared on next scan.
+  Snapshot(Vec<(usize, Vec<u8>)>),
+  /// Addresses still matching, with their value at the last scan stored
+  /// back to back in `previous`.
+  Candidates { addresses: Vec<usize>, previous: Vec<u8> },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ScanSummary {
+  id: u32,
+  /// Number of candidates, or `None` for an unknown-value snapshot.
+  total: Option<usize>,
+  /// More matches existed than are kept.
+  truncated: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ScanResult {
+  address: usize,
+  previous: Value,
+  /// Live value, `None` if it can no longer be read.
+  current: Option<Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ScanPage {
+  total: usize,
+  results: Vec<ScanResult>,
+}
+
+/// A scan result turned into something reusable: `probe` works with
+/// `read_value`, `freeze_value` and a profile's `values`, and `site` is set
+/// for static addresses inside Sky.exe, ready for a profile's `sites`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Promotion {
+  probe: Probe,
+  site: Option<String>,
+}
+
+impl Matcher {
+  fn new(value_type: ValueType, endian: Endian, value: &Value) -> Result<Self, String> {
+    if is_numeric(value_type) {
+      let target = value.as_f64().ok_or_else(|| "Scan value must be a number".to_string())?;
+      let tolerance = match value_type {
+        ValueType::F32 | ValueType::F64 => FLOAT_TOLERANCE,
+        _ => 0.0,
+      };
+      return Ok(Matcher::Number { min: target - tolerance, max: target + tolerance });
+    }
+    let bytes = value.to_bytes(endian);
+    if bytes.is_empty() || bytes.len() > value_type.size() {
+      return Err(format!("Scan value must be 1 to {} bytes", value_type.size()));
+    }
+    Ok(Matcher::Bytes(bytes))
+  }
+
+  fn matches(&self, value_type: ValueType, endian: Endian, bytes: &[u8]) -> bool {
+    match self {
+      Matcher::Bytes(expected) => bytes.starts_with(expected),
+      Matcher::Number { min, max } => {
+        number(value_type, endian, bytes).is_some_and(|value| value >= *min && value <= *max)
+      }
+    }
+  }
+}
+
+fn is_numeric(value_type: ValueType) -> bool {
+  !matches!(value_type, ValueType::Bytes { .. } | ValueType::String { .. })
+}
+
+fn number(value_type: ValueType, endian: Endian, bytes: &[u8]) -> Option<f64> {
+  value_type.decode(bytes, endian).ok()?.as_f64().filter(|value| !value.is_nan())
+}
+
+/// Numbers are only looked for at addresses aligned to their size.
+fn stride(value_type: ValueType) -> usize {
+  if is_numeric(value_type) {
+    value_type.size()
+  } else {
+    1
+  }
+}
+
+fn writable_regions(backend: &dyn MemoryBackend) -> Vec<(usize, usize)> {
+  backend
+    .regions()
+    .into_iter()
+    .filter(|region| region.readable && region.writable)
+    .map(|region| (region.base, region.size))
+    .collect()
+}
+
+impl Scan {
+  pub(crate) fn first(
+    backend: &dyn MemoryBackend,
+    value_type: ValueType,
+    endian: Endian,
+    scan: FirstScan,
+  ) -> Result<Self, String> {
+    let size = value_type.size();
+    if size == 0 {
+      return Err("Scan length must not be zero".to_string());
+    }
+    let matcher = match scan {
+      FirstScan::Exact { value } => Matcher::new(value_type, endian, &value)?,
+      FirstScan::Range { min, max } if is_numeric(value_type) => Matcher::Number { min, max },
+      FirstScan::Range { .. } => return Err("Range scans need a numeric type".to_string()),
+      FirstScan::Unknown if is_numeric(value_type) => {
+        return Self::snapshot(backend, value_type, endian)
+      }
+      FirstScan::Unknown => return Err("Unknown-value scans need a numeric type".to_string()),
+    };
+
+    let stride = stride(value_type);
+    let mut addresses = Vec::new();
+    let mut previous = Vec::new();
+    let mut truncated = false;
+    'regions: for (base, len) in writable_regions(backend) {
+      for chunk_start in (0..len).step_by(CHUNK) {
+        // Read a little past the chunk so values straddling its end are seen.
+        let read_len = (CHUNK + size - 1).min(len - chunk_start);
+        let Ok(bytes) = backend.read(base + chunk_start, read_len) else {
+          continue;
+        };
+        let mut offset = 0;
+        while offset < CHUNK && offset + size <= bytes.len() {
+          let value = &bytes[offset..offset + size];
+          if matcher.matches(value_type, endian, value) {
+            if addresses.len() == MAX_CANDIDATES {
+              truncated = true;
+              break 'regions;
+            }
+            addresses.push(base + chunk_start + offset);
+            previous.extend_from_slice(value);
+          }
+          offset += stride;
+        }
+      }
+    }
+    Ok(Scan { value_type, endian, results: Results::Candidates { addresses, previous }, truncated })
+  }
+
+  fn snapshot(
+    backend: &dyn MemoryBackend,
+    value_type: ValueType,
+    endian: Endian,
+  ) -> Result<Self, String> {
+    let mut snapshots = Vec::new();
+    let mut total = 0;
+    for (base, len) in writable_regions(backend) {
+      for chunk_start in (0..len).step_by(CHUNK) {
+        let read_len = CHUNK.min(len - chunk_start);
+        let Ok(bytes) = backend.read(base + chunk_start, read_len) else {
+          continue;
+        };
+        total += bytes.len();
+        if total > MAX_SNAPSHOT {
+          return Err(
+            "Too much writable memory for an unknown-value scan; start with an exact or range scan"
+              .to_string(),
+          );
+        }
+        snapshots.push((base + chunk_start, bytes));
+      }
+    }
+    Ok(Scan { value_type, endian, results: Results::Snapshot(snapshots), truncated: false })
+  }
+
+  pub(crate) fn next(&mut self, backend: &dyn MemoryBackend, scan: NextScan) -> Result<(), String> {
+    let value_type = self.value_type;
+    let endian = self.endian;
+    let size = value_type.size();
+    if matches!(scan, NextScan::Increased | NextScan::Decreased) && !is_numeric(value_type) {
+      return Err("Increased/decreased scans need a numeric type".to_string());
+    }
+    let matcher = match &scan {
+      NextScan::Equal { value } => Some(Matcher::new(value_type, endian, value)?),
+      _ => None,
+    };
+    let keep = |previous: &[u8], current: &[u8]| match &scan {
+      NextScan::Changed => previous != current,
+      NextScan::Unchanged => previous == current,
+      NextScan::Increased => compare(value_type, endian, previous, current, |a, b| b > a),
+      NextScan::Decreased => compare(value_type, endian, previous, current, |a, b| b < a),
+      NextScan::Equal { .. } => {
+        matcher.as_ref().is_some_and(|matcher| matcher.matches(value_type, endian, current))
+      }
+    };
+
+    let mut addresses = Vec::new();
+    let mut previous = Vec::new();
+    let mut truncated = false;
+    match &self.results {
+      Results::Snapshot(snapshots) => {
+        let stride = stride(value_type);
+        'snapshots: for (base, old) in snapshots {
+          let Ok(bytes) = backend.read(*base, old.len()) else {
+            continue;
+          };
+          for offset in (0..old.len().saturating_sub(size - 1)).step_by(stride) {
+            let current = &bytes[offset..offset + size];
+            if keep(&old[offset..offset + size], current) {
+              if addresses.len() == MAX_CANDIDATES {
+                truncated = true;
+                break 'snapshots;
+              }
+              addresses.push(base + offset);
+              previous.extend_from_slice(current);
+            }
+          }
+        }
+      }
+      Results::Candidates { addresses: old_addresses, previous: old_previous } => {
+        let mut index = 0;
+        while index < old_addresses.len() {
+          // Read neighbouring candidates in one go instead of one by one.
+          let start = old_addresses[index];
+          let mut end = index;
+          while end < old_addresses.len() && old_addresses[end] + size <= start + CHUNK {
+            end += 1;
+          }
+          let span = backend.read(start, old_addresses[end - 1] + size - start).ok();
+          for candidate in index..end {
+            let address = old_addresses[candidate];
+            let current = match &span {
+              Some(span) => span[address - start..address - start + size].to_vec(),
+              None => match backend.read(address, size) {
+                Ok(bytes) => bytes,
+                Err(_) => continue,
+              },
+            };
+            if keep(&old_previous[candidate * size..(candidate + 1) * size], &current) {
+              addresses.push(address);
+              previous.extend_from_slice(&current);
+            }
+          }
+          index = end;
+        }
+      }
+    }
+    self.results = Results::Candidates { addresses, previous };
+    self.truncated = truncated;
+    Ok(())
+  }
+
+  fn summary(&self, id: u32) -> ScanSummary {
+    ScanSummary {
+      id,
+      total: match &self.results {
+        Results::Snapshot(_) => None,
+        Results::Candidates { addresses, .. } => Some(addresses.len()),
+      },
+      truncated: self.truncated,
+    }
+  }
+
+  fn page(
+    &self,
+    backend: &dyn MemoryBackend,
+    offset: usize,
+    limit: usize,
+  ) -> Result<ScanPage, String> {
+    let Results::Candidates { addresses, previous } = &self.results else {
+      return Err("Unknown-value scans have no results until a next scan".to_string());
+    };
+    let size = self.value_type.size();
+    let end = addresses.len().min(offset.saturating_add(limit.min(MAX_PAGE)));
+    let mut results = Vec::new();
+    for index in offset.min(end)..end {
+      let address = addresses[index];
+      results.push(ScanResult {
+        address,
+        previous: self
+          .value_type
+          .decode(&previous[index * size..(index + 1) * size], self.endian)?,
+        current: backend
+          .read(address, size)
+          .ok()
+          .and_then(|bytes| self.value_type.decode(&bytes, self.endian).ok()),
+      });
+    }
+    Ok(ScanPage { total: addresses.len(), results })
+  }
+}
+
+fn compare(
+  value_type: ValueType,
+  endian: Endian,
+  previous: &[u8],
+  current: &[u8],
+  test: impl Fn(f64, f64) -> bool,
+) -> bool {
+  match (number(value_type, endian, previous), number(value_type, endian, current)) {
+    (Some(previous), Some(current)) => test(previous, current),
+    _ => false,
+  }
+}
+
+/// Describes `address`, one of `scan`'s candidates, as a target that survives
+/// restarts: an RVA when it is inside Sky.exe, otherwise a one-level pointer
+/// path from the nearest static pointer in Sky.exe's writable sections.
+fn promote(handle: &ProcessHandle, scan: &Scan, address: usize) -> Result<Promotion, String> {
+  let Results::Candidates { addresses, .. } = &scan.results else {
+    return Err("Unknown-value scans have no results until a next scan".to_string());
+  };
+  if !addresses.contains(&address) {
+    return Err(format!("{address:#x} is not a result of this scan"));
+  }
+  let image = handle.image.as_ref().ok_or_else(|| "Module layout unavailable".to_string())?;
+  let probe = |target| Probe { target, value_type: scan.value_type, endian: scan.endian };
+  if let Some(rva) = address.checked_sub(handle.base).filter(|rva| *rva < image.image_size as usize)
+  {
+    return Ok(Promotion {
+      probe: probe(Target::Offset(rva as u64)),
+      site: Some(format!("0x{rva:X}")),
+    });
+  }
+
+  let width = image.pointer_size();
+  let mut best: Option<(usize, usize)> = None;
+  for section in image.sections.iter().filter(|section| section.writable) {
+    let start = section.virtual_address as usize;
+    let Ok(bytes) = handle.backend.read(handle.base + start, section.end() - start) else {
+      continue;
+    };
+    for (index, raw) in bytes.chunks_exact(width).enumerate() {
+      let mut value = [0u8; 8];
+      value[..width].copy_from_slice(raw);
+      let pointer = u64::from_le_bytes(value) as usize;
+      let Some(offset) = address.checked_sub(pointer) else {
+        continue;
+      };
+      if pointer != 0 && offset < MAX_POINTER_OFFSET && best.map_or(true, |(_, best)| offset < best)
+      {
+        best = Some((start + index * width, offset));
+      }
+    }
+  }
+  let (slot, offset) = best.ok_or_else(|| {
+    format!("No static pointer to {address:#x} in Sky.exe; only one-level paths are searched")
+  })?;
+  Ok(Promotion {
+    probe: probe(Target::Pointer(PointerPath {
+      module: None,
+      base: slot,
+      offsets: vec![offset as i64],
+    })),
+    site: None,
+  })
+}
+
+/// Pid and backend of `session`, so a scan can walk its memory without
+/// holding the sessions lock.
+fn scan_backend(
+  state: &ProcessState,
+  session: Option<u32>,
+) -> Result<(u32, Arc<dyn MemoryBackend>), String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  Ok((handle.pid, Arc::clone(&handle.backend)))
+}
+
+/// Session `pid`, if it is still the attachment `backend` was taken from.
+fn rejoin<'a>(
+  sessions: &'a mut Sessions,
+  pid: u32,
+  backend: &Arc<dyn MemoryBackend>,
+) -> Result<&'a mut ProcessHandle, String> {
+  sessions
+    .get_mut(&pid)
+    .filter(|handle| Arc::ptr_eq(&handle.backend, backend))
+    .ok_or_else(|| "The session ended during the scan".to_string())
+}
+
+/// Starts a scan of the session's writable memory for `value_type`. Runs off
+/// the main thread, and without the sessions lock while memory is read.
+#[tauri::command(async)]
+pub(crate) fn first_scan(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  value_type: ValueType,
+  endian: Option<Endian>,
+  scan: FirstScan,
+) -> Result<ScanSummary, String> {
+  let (pid, backend) = scan_backend(&state, session)?;
+  let scan = Scan::first(backend.as_ref(), value_type, endian.unwrap_or_default(), scan)?;
+  let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+  let summary = scan.summary(id);
+  let mut guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  rejoin(&mut guard, pid, &backend)?.scans.insert(id, scan);
+  Ok(summary)
+}
+
+/// Narrows scan `id` to the candidates that pass `scan`. The scan is taken
+/// off the session while it runs, like `first_scan` without the lock.
+#[tauri::command(async)]
+pub(crate) fn next_scan(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  id: u32,
+  scan: NextScan,
+) -> Result<ScanSummary, String> {
+  let (pid, backend, mut existing) = {
+    let mut guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+    let handle = find_session_mut(&mut guard, session)?;
+    let existing = handle.scans.remove(&id).ok_or_else(|| format!("No scan {id}"))?;
+    (handle.pid, Arc::clone(&handle.backend), existing)
+  };
+  let result = existing.next(backend.as_ref(), scan);
+  let summary = existing.summary(id);
+  let mut guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  rejoin(&mut guard, pid, &backend)?.scans.insert(id, existing);
+  result.map(|()| summary)
+}
+
+/// Up to `limit` candidates of scan `id` from `offset`, with live values.
+#[tauri::command]
+pub(crate) fn scan_results(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  id: u32,
+  offset: usize,
+  limit: usize,
+) -> Result<ScanPage, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  let scan = handle.scans.get(&id).ok_or_else(|| format!("No scan {id}"))?;
+  scan.page(handle.backend.as_ref(), offset, limit)
+}
+
+#[tauri::command]
+pub(crate) fn promote_scan_result(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  id: u32,
+  address: usize,
+) -> Result<Promotion, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  let scan = handle.scans.get(&id).ok_or_else(|| format!("No scan {id}"))?;
+  promote(handle, scan, address)
+}
+
+#[tauri::command]
+pub(crate) fn close_scan(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  id: u32,
+) -> Result<(), String> {
+  let mut guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session_mut(&mut guard, session)?;
+  handle.scans.remove(&id).map(|_| ()).ok_or_else(|| format!("No scan {id}"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::backend::fake::{FakeProcess, SKY_BASE};
+
+  const HEAP: usize = 0x2000_0000;
+  const VALUE: u32 = 777_777;
+
+  /// Sky.exe plus a heap page holding `VALUE` at two aligned addresses and
+  /// one unaligned one, and floats from 0x20.
+  fn process() -> FakeProcess {
+    let mut heap = vec![0u8; 0x1000];
+    for offset in [0x10, 0x40, 0x81] {
+      heap[offset..offset + 4].copy_from_slice(&VALUE.to_le_bytes());
+    }
+    heap[0x20..0x24].copy_from_slice(&1.5f32.to_le_bytes());
+    FakeProcess::sky(1).with_region(HEAP, heap)
+  }
+
+  fn addresses(scan: &Scan) -> &[usize] {
+    match &scan.results {
+      Results::Candidates { addresses, .. } => addresses,
+      Results::Snapshot(_) => &[],
+    }
+  }
+
+  fn exact(value: u32) -> FirstScan {
+    FirstScan::Exact { value: Value::U32(value) }
+  }
+
+  #[test]
+  fn first_scan_finds_aligned_matches() {
+    let process = process();
+    let scan = Scan::first(&process, ValueType::U32, Endian::Little, exact(VALUE)).unwrap();
+    assert_eq!(addresses(&scan), [HEAP + 0x10, HEAP + 0x40]);
+
+    let range = FirstScan::Range { min: 777_770.0, max: 777_780.0 };
+    let scan = Scan::first(&process, ValueType::U32, Endian::Little, range).unwrap();
+    assert_eq!(addresses(&scan), [HEAP + 0x10, HEAP + 0x40]);
+    let summary = scan.summary(7);
+    assert_eq!((summary.total, summary.truncated), (Some(2), false));
+  }
+
+  #[test]
+  fn next_scan_narrows_candidates() {
+    let process = process();
+    let mut scan = Scan::first(&process, ValueType::U32, Endian::Little, exact(VALUE)).unwrap();
+    process.write(HEAP + 0x40, &777_800u32.to_le_bytes()).unwrap();
+
+    scan.next(&process, NextScan::Unchanged).unwrap();
+    assert_eq!(addresses(&scan), [HEAP + 0x10]);
+
+    let mut scan = Scan::first(&process, ValueType::U32, Endian::Little, exact(VALUE)).unwrap();
+    process.write(HEAP + 0x10, &777_700u32.to_le_bytes()).unwrap();
+    scan.next(&process, NextScan::Decreased).unwrap();
+    assert_eq!(addresses(&scan), [HEAP + 0x10]);
+    scan.next(&process, NextScan::Equal { value: Value::U32(777_700) }).unwrap();
+    assert_eq!(addresses(&scan), [HEAP + 0x10]);
+    scan.next(&process, NextScan::Changed).unwrap();
+    assert!(addresses(&scan).is_empty());
+  }
+
+  #[test]
+  fn unknown_scan_compares_against_a_snapshot() {
+    let process = process();
+    let unknown = FirstScan::Unknown;
+    let mut scan = Scan::first(&process, ValueType::F32, Endian::Little, unknown).unwrap();
+    assert_eq!(scan.summary(1).total, None);
+    assert!(scan.page(&process, 0, 10).is_err());
+
+    process.write(HEAP + 0x20, &2.5f32.to_le_bytes()).unwrap();
+    scan.next(&process, NextScan::Increased).unwrap();
+    assert_eq!(addresses(&scan), [HEAP + 0x20]);
+  }
+
+  #[test]
+  fn page_shows_previous_and_live_values() {
+    let process = process();
+    let scan = Scan::first(&process, ValueType::U32, Endian::Little, exact(VALUE)).unwrap();
+    process.write(HEAP + 0x40, &5u32.to_le_bytes()).unwrap();
+
+    let page = scan.page(&process, 1, 10).unwrap();
+    assert_eq!(page.total, 2);
+    assert_eq!(page.results.len(), 1);
+    let result = &page.results[0];
+    assert_eq!(result.address, HEAP + 0x40);
+    assert!(matches!(result.previous, Value::U32(VALUE)));
+    assert!(matches!(result.current, Some(Value::U32(5))));
+    assert!(scan.page(&process, 5, 10).unwrap().results.is_empty());
+  }
+
+  #[test]
+  fn promotes_static_addresses_to_sites() {
+    let process = process();
+    let handle = process.attach(&[]);
+    let scan = Scan::first(&process, ValueType::U32, Endian::Little, exact(1234)).unwrap();
+    assert!(addresses(&scan).contains(&(SKY_BASE + 0x600)));
+
+    let promotion = promote(&handle, &scan, SKY_BASE + 0x600).unwrap();
+    assert_eq!(promotion.site.as_deref(), Some("0x600"));
+    assert!(matches!(promotion.probe.target, Target::Offset(0x600)));
+  }
+
+  #[test]
+  fn promotes_heap_addresses_through_static_pointers() {
+    let process = process();
+    let handle = process.attach(&[]);
+    let scan = Scan::first(&process, ValueType::U32, Endian::Little, exact(VALUE)).unwrap();
+    let error = promote(&handle, &scan, HEAP + 0x40).err().unwrap();
+    assert!(error.contains("No static pointer"), "{error}");
+
+    process.write(SKY_BASE + 0x620, &(HEAP as u64).to_le_bytes()).unwrap();
+    let promotion = promote(&handle, &scan, HEAP + 0x40).unwrap();
+    assert!(promotion.site.is_none());
+    let Target::Pointer(path) = &promotion.probe.target else {
+      panic!("expected a pointer path");
+    };
+    assert_eq!((path.base, path.offsets.as_slice()), (0x620, [0x40].as_slice()));
+  }
+
+  #[test]
+  fn only_promotes_results_of_the_scan() {
+    let process = process();
+    let handle = process.attach(&[]);
+    let scan = Scan::first(&process, ValueType::U32, Endian::Little, exact(VALUE)).unwrap();
+    let error = promote(&handle, &scan, SKY_BASE + 0x600).err().unwrap();
+    assert!(error.contains("is not a result of this scan"), "{error}");
+
+    let unknown = Scan::first(&process, ValueType::U32, Endian::Little, FirstScan::Unknown);
+    let error = promote(&handle, &unknown.unwrap(), HEAP + 0x10).err().unwrap();
+    assert!(error.contains("no results until a next scan"), "{error}");
+  }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -48,11 +49,38 @@ pub(crate) fn load_settings(app: AppHandle) -> Result<AppSettings, String> {
   Ok(settings)
 }
 
-#[tauri::command]
-pub(crate) fn save_settings(app: AppHandle, settings: AppSettings) -> Result<(), String> {
-  let path = settings_path(&app)?;
+/// Settings for backend use, falling back to defaults if the file is missing
+/// or unreadable.
+pub(crate) fn current_settings(app: &AppHandle) -> AppSettings {
+  load_settings(app.clone()).unwrap_or_default()
+}
+
+fn write_settings(app: &AppHandle, settings: &AppSettings) -> Result<(), String> {
+  let path = settings_path(app)?;
   let payload =
-    serde_json::to_string_pretty(&settings).map_err(|_| "Failed to encode settings".to_string())?;
+    serde_json::to_string_pretty(settings).map_err(|_| "Failed to encode settings".to_string())?;
   fs::write(path, payload).map_err(|_| "Failed to write settings file".to_string())?;
   Ok(())
 }
+
+/// Records a feature as active with its parameters, or forgets it (`None`).
+pub(crate) fn remember_feature(
+  app: &AppHandle,
+  id: &str,
+  params: Option<HashMap<String, f32>>,
+) -> Result<(), String> {
+  let mut settings = current_settings(app);
+  match params {
+    Some(params) => settings.active_features.insert(id.to_string(), params),
+    None => settings.active_features.remove(id),
+  };
+  write_settings(app, &settings)
+}
+
+/// The set of active features is owned by the backend, so whatever the UI
+/// sends for it is replaced by what is on disk.
+#[tauri::command]
+pub(crate) fn save_settings(app: AppHandle, mut settings: AppSettings) -> Result<(), String> {
+  settings.active_features = current_settings(&app).active_features;
+  write_settings(&app, &settings)
+}
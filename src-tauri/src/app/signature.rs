@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::backend::MemoryBackend;
+
+const SCAN_CHUNK: usize = 0x10_0000;
+
+/// IDA-style byte pattern, e.g. `48 8B 05 ?? ?? ?? ?? 84 C0`. A single nibble
+/// can also be masked, so `0?` matches any byte from `00` to `0F`.
+pub(crate) struct Pattern {
+  text: String,
+  bytes: Vec<(u8, u8)>,
+}
+
+fn parse_nibble(nibble: char) -> Option<(u8, u8)> {
+  match nibble {
+    '?' => Some((0, 0)),
+    _ => nibble.to_digit(16).map(|value| (value as u8, 0xF)),
+  }
+}
+
+impl Pattern {
+  pub(crate) fn parse(text: &str) -> Result<Self, String> {
+    let bytes = text
+      .split_whitespace()
+      .map(|token| {
+        let mut nibbles = token.chars();
+        let parsed = match (nibbles.next(), nibbles.next(), nibbles.next()) {
+          (Some('?'), None, None) => Some((0, 0)),
+          (Some(high), Some(low), None) => parse_nibble(high)
+            .zip(parse_nibble(low))
+            .map(|((hv, hm), (lv, lm))| (hv << 4 | lv, hm << 4 | lm)),
+          _ => None,
+        };
+        parsed.ok_or_else(|| format!("Invalid pattern byte `{token}`"))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    if bytes.is_empty() {
+      return Err("Empty pattern".to_string());
+    }
+    Ok(Self {
+      text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+      bytes,
+    })
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    self.bytes.len()
+  }
+
+  /// Whether every byte is masked, so the pattern only pins a length.
+  pub(crate) fn is_wildcard(&self) -> bool {
+    self.bytes.iter().all(|(_, mask)| *mask == 0)
+  }
+
+  pub(crate) fn matches(&self, haystack: &[u8]) -> bool {
+    haystack.len() >= self.bytes.len()
+      && self
+        .bytes
+        .iter()
+        .zip(haystack)
+        .all(|((value, mask), actual)| actual & mask == *value)
+  }
+
+  /// Offsets of every match that starts inside `haystack[..limit]`.
+  fn find_all<'a>(
+    &'a self,
+    haystack: &'a [u8],
+    limit: usize,
+  ) -> impl Iterator<Item = usize> + 'a {
+    let last = haystack.len().saturating_sub(self.bytes.len() - 1).min(limit);
+    (0..last).filter(move |offset| self.matches(&haystack[*offset..]))
+  }
+}
+
+impl fmt::Display for Pattern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.text)
+  }
+}
+
+/// How to turn the address of a pattern match into the address of the site.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum Adjustment {
+  /// Site is at `match + offset`.
+  Offset { offset: isize },
+  /// Match is an instruction with a rel32 operand at `match + displacement`;
+  /// the site is the operand's target, relative to the end of the instruction.
+  #[serde(rename_all = "camelCase")]
+  RipRelative { displacement: usize, length: usize },
+}
+
+impl Default for Adjustment {
+  fn default() -> Self {
+    Self::Offset { offset: 0 }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Signature {
+  pub(crate) pattern: String,
+  #[serde(default)]
+  pub(crate) adjust: Adjustment,
+}
+
+fn apply_adjustment(
+  backend: &dyn MemoryBackend,
+  address: usize,
+  adjust: Adjustment,
+) -> Result<usize, String> {
+  match adjust {
+    Adjustment::Offset { offset } => address
+      .checked_add_signed(offset)
+      .ok_or_else(|| "Adjusted address overflows".to_string()),
+    Adjustment::RipRelative {
+      displacement,
+      length,
+    } => {
+      let bytes = backend.read(address + displacement, 4)?;
+      let relative = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+      (address + length)
+        .checked_add_signed(relative as isize)
+        .ok_or_else(|| "RIP-relative target overflows".to_string())
+    }
+  }
+}
+
+/// Scans every `(start, end)` range once for all signatures and resolves each
+/// to an address. A signature must match exactly one site; zero or several
+/// matches are reported as that feature's error.
+pub(crate) fn scan_ranges(
+  backend: &dyn MemoryBackend,
+  ranges: &[(usize, usize)],
+  signatures: &[(&str, &Signature)],
+) -> HashMap<String, Result<usize, String>> {
+  let mut results = HashMap::new();
+  let mut patterns = Vec::new();
+  for (name, signature) in signatures {
+    match Pattern::parse(&signature.pattern) {
+      Ok(pattern) => patterns.push((*name, signature, pattern)),
+      Err(error) => {
+        results.insert(name.to_string(), Err(error));
+      }
+    }
+  }
+  let overlap = patterns.iter().map(|(_, _, pattern)| pattern.len()).max().unwrap_or(1) - 1;
+  let mut matches: HashMap<&str, Vec<usize>> = HashMap::new();
+
+  for &(start, end) in ranges {
+    let mut chunk_start = start;
+    while chunk_start < end {
+      let chunk_len = SCAN_CHUNK.min(end - chunk_start);
+      let read_len = (chunk_len + overlap).min(end - chunk_start);
+      if let Ok(chunk) = backend.read(chunk_start, read_len) {
+        for (name, _, pattern) in &patterns {
+          let found = matches.entry(name).or_default();
+          if found.len() > 1 {
+            continue;
+          }
+          found.extend(
+            pattern
+              .find_all(&chunk, chunk_len)
+              .take(2)
+              .map(|offset| chunk_start + offset),
+          );
+        }
+      }
+      chunk_start += chunk_len;
+    }
+  }
+
+  for (name, signature, _) in &patterns {
+    let result = match matches.get(name).map(Vec::as_slice).unwrap_or_default() {
+      [address] => apply_adjustment(backend, *address, signature.adjust),
+      [] => Err("Signature not found".to_string()),
+      _ => Err("Signature matches more than one site".to_string()),
+    };
+    results.insert(name.to_string(), result);
+  }
+  results
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app::backend::fake::{FakeProcess, SKY_BASE};
+
+  fn signature(pattern: &str, adjust: Adjustment) -> Signature {
+    Signature {
+      pattern: pattern.to_string(),
+      adjust,
+    }
+  }
+
+  fn scan(
+    process: &FakeProcess,
+    ranges: &[(usize, usize)],
+    signature: &Signature,
+  ) -> Result<usize, String> {
+    scan_ranges(process, ranges, &[("site", signature)]).remove("site").unwrap()
+  }
+
+  #[test]
+  fn parses_wildcards_and_nibble_masks() {
+    let pattern = Pattern::parse("48  8B ?? ? 0? ?F").unwrap();
+    assert_eq!(pattern.len(), 6);
+    assert_eq!(pattern.to_string(), "48 8B ?? ? 0? ?F");
+    assert!(pattern.matches(&[0x48, 0x8B, 0xAA, 0xBB, 0x0C, 0x3F]));
+    assert!(!pattern.matches(&[0x48, 0x8B, 0xAA, 0xBB, 0x1C, 0x3F]), "high nibble is pinned");
+    assert!(!pattern.matches(&[0x48, 0x8B, 0xAA, 0xBB, 0x0C, 0x3E]), "low nibble is pinned");
+    assert!(!pattern.matches(&[0x48, 0x8B, 0xAA]), "haystack is shorter than the pattern");
+    assert!(Pattern::parse("?? ??").unwrap().is_wildcard());
+    assert!(!pattern.is_wildcard());
+  }
+
+  #[test]
+  fn rejects_malformed_patterns() {
+    assert_eq!(Pattern::parse("48 8G").err().unwrap(), "Invalid pattern byte `8G`");
+    assert_eq!(Pattern::parse("488B").err().unwrap(), "Invalid pattern byte `488B`");
+    assert_eq!(Pattern::parse("  ").err().unwrap(), "Empty pattern");
+  }
+
+  #[test]
+  fn finds_a_match_across_a_chunk_boundary() {
+    let mut bytes = vec![0u8; SCAN_CHUNK + 0x10];
+    bytes[SCAN_CHUNK - 2..SCAN_CHUNK + 2].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+    let process = FakeProcess::new(1).with_region(0x1000, bytes);
+    let ranges = [(0x1000, 0x1000 + SCAN_CHUNK + 0x10)];
+    let found = scan(&process, &ranges, &signature("DE AD BE EF", Adjustment::default()));
+    assert_eq!(found, Ok(0x1000 + SCAN_CHUNK - 2));
+  }
+
+  #[test]
+  fn applies_offset_and_rip_relative_adjustments() {
+    let process = FakeProcess::sky(1);
+    let text = [(SKY_BASE + 0x200, SKY_BASE + 0x400)];
+    // `call` follows the `mov rax, [rip+0x1F9]` at 0x200, so this matches once.
+    let pattern = "48 8B 05 ?? ?? ?? ?? E8";
+    let offset = signature(pattern, Adjustment::Offset { offset: 7 });
+    assert_eq!(scan(&process, &text, &offset), Ok(SKY_BASE + 0x207));
+    let rip = Adjustment::RipRelative {
+      displacement: 3,
+      length: 7,
+    };
+    assert_eq!(scan(&process, &text, &signature(pattern, rip)), Ok(SKY_BASE + 0x400));
+  }
+
+  #[test]
+  fn reports_missing_and_ambiguous_signatures() {
+    let process = FakeProcess::sky(1);
+    let text = [(SKY_BASE + 0x200, SKY_BASE + 0x400)];
+    let missing = signature("0F 0B", Adjustment::default());
+    assert_eq!(scan(&process, &text, &missing).unwrap_err(), "Signature not found");
+    // .text is padded with int3.
+    let padding = signature("CC CC", Adjustment::default());
+    assert_eq!(
+      scan(&process, &text, &padding).unwrap_err(),
+      "Signature matches more than one site"
+    );
+    let invalid = signature("XX", Adjustment::default());
+    assert_eq!(scan(&process, &text, &invalid).unwrap_err(), "Invalid pattern byte `XX`");
+  }
+}
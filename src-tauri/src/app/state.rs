@@ -1,37 +1,218 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
 
 use serde::{Deserialize, Serialize};
 
+use super::backend::MemoryBackend;
+use super::features::FeatureDef;
+use super::freeze::Freeze;
+use super::scanner::Scan;
+use super::pe::PeImage;
+use super::pointer::Target;
+use super::profile::{BuildIdentity, OffsetProfile};
+use super::signature::Pattern;
+
+/// Bytes overwritten at one range, keyed by its start in
+/// [`ProcessHandle::patches`]. Features that write the same bytes to the same
+/// range share the record; the original goes back when the last owner
+/// releases it.
+#[derive(Clone)]
+pub(crate) struct PatchRecord {
+  /// What the range held before it was patched; its length is the range's.
+  pub(crate) original: Vec<u8>,
+  pub(crate) patched: Vec<u8>,
+  /// Ids of the features holding the patch, one reference each.
+  pub(crate) owners: BTreeSet<String>,
+}
+
+impl PatchRecord {
+  pub(crate) fn overlaps(&self, start: usize, address: usize, len: usize) -> bool {
+    start < address + len && address < start + self.original.len()
+  }
+}
+
 pub(crate) struct ProcessHandle {
-  pub(crate) handle: isize,
+  pub(crate) backend: Arc<dyn MemoryBackend>,
   pub(crate) pid: u32,
   pub(crate) base: usize,
-  pub(crate) original: HashMap<usize, Vec<u8>>,
+  pub(crate) patches: BTreeMap<usize, PatchRecord>,
+  pub(crate) sites: HashMap<String, Result<usize, String>>,
+  pub(crate) profile: OffsetProfile,
+  pub(crate) build: Option<BuildIdentity>,
+  pub(crate) image: Option<PeImage>,
+  /// Features currently enabled in this session, with their parameters.
+  pub(crate) active: BTreeMap<String, HashMap<String, f32>>,
+  /// Values locked by the freeze workers, keyed by freeze id.
+  pub(crate) freezes: BTreeMap<u32, Freeze>,
+  /// Value scans in progress, keyed by scan id.
+  pub(crate) scans: BTreeMap<u32, Scan>,
 }
 
-impl Drop for ProcessHandle {
-  fn drop(&mut self) {
-    unsafe {
-      windows_sys::Win32::Foundation::CloseHandle(self.handle);
+impl ProcessHandle {
+  pub(crate) fn new(backend: Box<dyn MemoryBackend>, base: usize, profile: OffsetProfile) -> Self {
+    Self {
+      pid: backend.pid(),
+      backend: Arc::from(backend),
+      base,
+      patches: BTreeMap::new(),
+      sites: HashMap::new(),
+      profile,
+      build: None,
+      image: None,
+      active: BTreeMap::new(),
+      freezes: BTreeMap::new(),
+      scans: BTreeMap::new(),
+    }
+  }
+
+  /// Rejects patches that would not land entirely inside one section of the
+  /// module, such as offsets into the PE headers or past the image end.
+  pub(crate) fn check_range(&self, offset: usize, len: usize) -> Result<(), String> {
+    let Some(image) = &self.image else {
+      return Ok(());
+    };
+    if image.section_containing(offset, len).is_none() {
+      return Err(format!("Offset {offset:#x} (+{len}) is outside Sky.exe's sections"));
+    }
+    Ok(())
+  }
+
+  /// Absolute address of a resolved feature site.
+  pub(crate) fn site(&self, name: &str) -> Result<usize, String> {
+    match self.sites.get(name) {
+      Some(Ok(address)) => Ok(*address),
+      Some(Err(error)) => Err(format!("{name} is unavailable: {error}")),
+      None => Err(format!("Unknown feature site {name}")),
     }
   }
 }
 
+/// Attached clients keyed by pid, which doubles as the session id.
+pub(crate) type Sessions = BTreeMap<u32, ProcessHandle>;
+
 pub(crate) struct ProcessState {
-  pub(crate) inner: Mutex<Option<ProcessHandle>>,
+  pub(crate) sessions: Mutex<Sessions>,
 }
 
-#[derive(Serialize)]
+/// Pid of the session a command targets. Without an explicit `session` the
+/// only attached client is used, so single-client callers need not pass one.
+pub(crate) fn session_id(sessions: &Sessions, session: Option<u32>) -> Result<u32, String> {
+  match session {
+    Some(pid) if sessions.contains_key(&pid) => Ok(pid),
+    Some(pid) => Err(format!("No session for pid {pid}")),
+    None => {
+      let mut pids = sessions.keys();
+      match (pids.next(), pids.next()) {
+        (Some(pid), None) => Ok(*pid),
+        (None, _) => Err("Process not attached".to_string()),
+        _ => Err("Several clients are attached; pass a session id".to_string()),
+      }
+    }
+  }
+}
+
+pub(crate) fn find_session(
+  sessions: &Sessions,
+  session: Option<u32>,
+) -> Result<&ProcessHandle, String> {
+  let pid = session_id(sessions, session)?;
+  sessions.get(&pid).ok_or_else(|| "Process not attached".to_string())
+}
+
+pub(crate) fn find_session_mut(
+  sessions: &mut Sessions,
+  session: Option<u32>,
+) -> Result<&mut ProcessHandle, String> {
+  let pid = session_id(sessions, session)?;
+  sessions.get_mut(&pid).ok_or_else(|| "Process not attached".to_string())
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct UnresolvedSite {
+  pub(crate) name: String,
+  pub(crate) error: String,
+}
+
+#[derive(Serialize, Clone)]
 pub(crate) struct AttachResponse {
   pub(crate) pid: u32,
   pub(crate) base: usize,
+  pub(crate) profile: String,
+  pub(crate) unresolved: Vec<UnresolvedSite>,
+}
+
+/// One write of a feature, built from the registry in `features.rs`.
+pub(crate) struct FeatureOperation {
+  pub(crate) site: String,
+  pub(crate) target: Target,
+  pub(crate) bytes: Vec<u8>,
+  /// What the site must hold before it is first patched.
+  pub(crate) expected: Pattern,
+}
+
+/// Why `apply_feature` failed. `operation` is the index of the operation that
+/// failed, or `None` if nothing was written; `unreverted` lists operations
+/// whose rollback failed and are still applied.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FeatureError {
+  pub(crate) operation: Option<usize>,
+  pub(crate) message: String,
+  pub(crate) unreverted: Vec<usize>,
+}
+
+impl From<String> for FeatureError {
+  fn from(message: String) -> Self {
+    Self {
+      operation: None,
+      message,
+      unreverted: Vec::new(),
+    }
+  }
+}
+
+/// A registry entry plus whether it can be toggled right now.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FeatureInfo {
+  #[serde(flatten)]
+  pub(crate) feature: &'static FeatureDef,
+  pub(crate) available: bool,
+  pub(crate) unavailable_reason: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RestoreFailure {
+  pub(crate) offset: usize,
+  pub(crate) error: String,
+}
+
+/// Outcome of releasing a process: how many patched sites were restored or
+/// deliberately left in place, and which ones could not be restored.
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DetachResponse {
+  pub(crate) restored: usize,
+  pub(crate) kept: usize,
+  pub(crate) failed: Vec<RestoreFailure>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SessionInfo {
+  pub(crate) pid: u32,
+  pub(crate) profile: String,
+  pub(crate) active: Vec<String>,
 }
 
+/// `pid` is the only session's pid, or `None` when zero or several clients
+/// are attached.
 #[derive(Serialize)]
 pub(crate) struct StatusResponse {
   pub(crate) attached: bool,
   pub(crate) pid: Option<u32>,
+  pub(crate) sessions: Vec<SessionInfo>,
 }
 
 #[derive(Serialize)]
@@ -40,26 +221,16 @@ pub(crate) struct ForegroundWindow {
   pub(crate) class_name: String,
 }
 
+/// The active offset profile. Site offsets are flattened next to the profile
+/// metadata and are `null` when a site could not be resolved.
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct OffsetsResponse {
-  pub(crate) invincibility: u64,
-  pub(crate) run_speed: u64,
+  pub(crate) profile: String,
+  pub(crate) build: Option<BuildIdentity>,
   pub(crate) default_run_speed: f32,
-  pub(crate) infinite_energy: u64,
-  pub(crate) infinite_breath: u64,
-  pub(crate) anti_rain_drain: u64,
-  pub(crate) anti_afk: u64,
-  pub(crate) super_jump: u64,
-  pub(crate) super_swim: u64,
-  pub(crate) super_flight: u64,
-  pub(crate) anti_sink: u64,
-  pub(crate) disable_cam_snap: u64,
-  pub(crate) free_zoom: u64,
-  pub(crate) disable_cam_rotation: u64,
-  pub(crate) first_person: u64,
-  pub(crate) show_cursor: u64,
-  pub(crate) super_run_patch: u64,
+  #[serde(flatten)]
+  pub(crate) offsets: BTreeMap<String, Option<u64>>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -72,6 +243,13 @@ pub(crate) struct AppSettings {
   pub(crate) app_scale: f32,
   pub(crate) memory_saver: bool,
   pub(crate) feature_hotkeys: HashMap<String, String>,
+  /// Leave features applied in the game on detach and exit.
+  pub(crate) keep_patches_on_detach: bool,
+  /// Attach as soon as the game is running.
+  pub(crate) auto_attach: bool,
+  /// Features active at the end of the last session, with their parameters.
+  /// Written by `set_feature` only.
+  pub(crate) active_features: BTreeMap<String, HashMap<String, f32>>,
 }
 
 impl Default for AppSettings {
@@ -84,6 +262,9 @@ impl Default for AppSettings {
       app_scale: 1.0,
       memory_saver: false,
       feature_hotkeys: HashMap::new(),
+      keep_patches_on_detach: false,
+      auto_attach: false,
+      active_features: BTreeMap::new(),
     }
   }
 }
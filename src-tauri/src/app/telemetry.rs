@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::settings;
+use super::state::ProcessState;
+use super::value::{Probe, Value};
+
+pub(crate) const DEFAULT_TICK_MS: u64 = 250;
+const MIN_TICK_MS: u64 = 16;
+/// How often workers re-read settings to notice `memorySaver` being enabled.
+const SETTINGS_POLL: Duration = Duration::from_secs(1);
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+/// Live subscription ids. A worker runs for as long as its id is in here.
+static SUBSCRIPTIONS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+/// One tick's worth of values, emitted as `telemetry`. Values that could not
+/// be read (e.g. a null pointer on a loading screen) are in `errors` instead.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TelemetryBatch {
+  subscription: u32,
+  session: u32,
+  timestamp_ms: u64,
+  values: BTreeMap<String, Value>,
+  errors: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TelemetryStopped {
+  subscription: u32,
+  reason: String,
+}
+
+/// Starts a worker that reads `values` from session `pid` every `tick_ms`.
+pub(crate) fn subscribe(
+  app: &AppHandle,
+  pid: u32,
+  values: BTreeMap<String, Probe>,
+  tick_ms: u64,
+) -> Result<u32, String> {
+  if values.is_empty() {
+    return Err("No telemetry values declared for this profile".to_string());
+  }
+  if settings::current_settings(app).memory_saver {
+    return Err("Telemetry is off while Memory Saver is enabled".to_string());
+  }
+  let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+  SUBSCRIPTIONS
+    .lock()
+    .map_err(|_| "State lock error".to_string())?
+    .insert(id);
+  let tick = Duration::from_millis(tick_ms.max(MIN_TICK_MS));
+  spawn_worker(app.clone(), id, pid, values, tick);
+  Ok(id)
+}
+
+pub(crate) fn unsubscribe(id: u32) -> bool {
+  SUBSCRIPTIONS
+    .lock()
+    .is_ok_and(|mut subscriptions| subscriptions.remove(&id))
+}
+
+fn spawn_worker(
+  app: AppHandle,
+  id: u32,
+  pid: u32,
+  values: BTreeMap<String, Probe>,
+  tick: Duration,
+) {
+  thread::spawn(move || {
+    let mut settings_checked = Instant::now();
+    let reason = loop {
+      if !SUBSCRIPTIONS.lock().is_ok_and(|subscriptions| subscriptions.contains(&id)) {
+        return;
+      }
+      if settings_checked.elapsed() >= SETTINGS_POLL {
+        settings_checked = Instant::now();
+        if settings::current_settings(&app).memory_saver {
+          break "Memory Saver is enabled";
+        }
+      }
+      let state = app.state::<ProcessState>();
+      let Ok(guard) = state.sessions.lock() else {
+        break "State lock error";
+      };
+      let Some(handle) = guard.get(&pid) else {
+        break "Session ended";
+      };
+      let mut batch = TelemetryBatch {
+        subscription: id,
+        session: pid,
+        timestamp_ms: SystemTime::now()
+          .duration_since(UNIX_EPOCH)
+          .map_or(0, |since| since.as_millis() as u64),
+        values: BTreeMap::new(),
+        errors: BTreeMap::new(),
+      };
+      for (name, probe) in &values {
+        match probe.read(handle) {
+          Ok(value) => {
+            batch.values.insert(name.clone(), value);
+          }
+          Err(error) => {
+            batch.errors.insert(name.clone(), error);
+          }
+        }
+      }
+      drop(guard);
+      let _ = app.emit("telemetry", batch);
+      thread::sleep(tick);
+    };
+    unsubscribe(id);
+    let stopped = TelemetryStopped {
+      subscription: id,
+      reason: reason.to_string(),
+    };
+    let _ = app.emit("telemetry-stopped", stopped);
+  });
+}
@@ -0,0 +1,278 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::constants::MAX_READ_LEN;
+use super::pointer::Target;
+use super::state::ProcessHandle;
+
+/// Byte order of a value in the game's memory. Sky.exe is little-endian, but
+/// some network-facing fields are stored big-endian.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Endian {
+  #[default]
+  Little,
+  Big,
+}
+
+/// What to read, e.g. `{ "type": "f32" }` or `{ "type": "string", "len": 32 }`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum ValueType {
+  U8,
+  I8,
+  U16,
+  I32,
+  U32,
+  I64,
+  U64,
+  F32,
+  F64,
+  Bool,
+  Bytes {
+    #[serde(deserialize_with = "bounded_len")]
+    len: usize,
+  },
+  /// Fixed-size UTF-8 buffer; the value ends at the first NUL.
+  String {
+    #[serde(deserialize_with = "bounded_len")]
+    len: usize,
+  },
+}
+
+/// Lengths come from the UI and profiles, so they are capped before anything
+/// allocates a buffer of that size.
+fn bounded_len<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+  let len = usize::deserialize(deserializer)?;
+  if len == 0 || len > MAX_READ_LEN {
+    return Err(serde::de::Error::custom(format!(
+      "len must be between 1 and {MAX_READ_LEN:#x}"
+    )));
+  }
+  Ok(len)
+}
+
+/// A typed value at a target, e.g. a player coordinate declared in a profile:
+/// `{ "target": { "base": "0x3A1B2C0", "offsets": ["0x18"] }, "type": "f32" }`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Probe {
+  pub(crate) target: Target,
+  #[serde(flatten)]
+  pub(crate) value_type: ValueType,
+  #[serde(default)]
+  pub(crate) endian: Endian,
+}
+
+impl Probe {
+  pub(crate) fn read(&self, handle: &ProcessHandle) -> Result<Value, String> {
+    let size = self.value_type.size();
+    let address = handle.resolve_target(&self.target, size)?;
+    let bytes = handle.backend.read(address, size)?;
+    self.value_type.decode(&bytes, self.endian)
+  }
+}
+
+/// A typed value, e.g. `{ "type": "f32", "value": 20 }`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub(crate) enum Value {
+  U8(u8),
+  I8(i8),
+  U16(u16),
+  I32(i32),
+  U32(u32),
+  I64(i64),
+  U64(u64),
+  F32(f32),
+  F64(f64),
+  Bool(bool),
+  Bytes(Vec<u8>),
+  /// Written as its UTF-8 bytes only; include a trailing `\0` to terminate it.
+  String(String),
+}
+
+macro_rules! encode {
+  ($value:expr, $endian:expr) => {
+    match $endian {
+      Endian::Little => $value.to_le_bytes().to_vec(),
+      Endian::Big => $value.to_be_bytes().to_vec(),
+    }
+  };
+}
+
+macro_rules! decode {
+  ($ty:ty, $bytes:expr, $endian:expr) => {{
+    let raw: [u8; std::mem::size_of::<$ty>()] =
+      $bytes.try_into().map_err(|_| "Value read was truncated".to_string())?;
+    match $endian {
+      Endian::Little => <$ty>::from_le_bytes(raw),
+      Endian::Big => <$ty>::from_be_bytes(raw),
+    }
+  }};
+}
+
+impl Value {
+  pub(crate) fn to_bytes(&self, endian: Endian) -> Vec<u8> {
+    match self {
+      Value::U8(value) => vec![*value],
+      Value::I8(value) => value.to_le_bytes().to_vec(),
+      Value::U16(value) => encode!(value, endian),
+      Value::I32(value) => encode!(value, endian),
+      Value::U32(value) => encode!(value, endian),
+      Value::I64(value) => encode!(value, endian),
+      Value::U64(value) => encode!(value, endian),
+      Value::F32(value) => encode!(value, endian),
+      Value::F64(value) => encode!(value, endian),
+      Value::Bool(value) => vec![u8::from(*value)],
+      Value::Bytes(bytes) => bytes.clone(),
+      Value::String(text) => text.as_bytes().to_vec(),
+    }
+  }
+
+  /// The value as a number, for range and comparison scans.
+  pub(crate) fn as_f64(&self) -> Option<f64> {
+    Some(match self {
+      Value::U8(value) => f64::from(*value),
+      Value::I8(value) => f64::from(*value),
+      Value::U16(value) => f64::from(*value),
+      Value::I32(value) => f64::from(*value),
+      Value::U32(value) => f64::from(*value),
+      Value::I64(value) => *value as f64,
+      Value::U64(value) => *value as f64,
+      Value::F32(value) => f64::from(*value),
+      Value::F64(value) => *value,
+      Value::Bool(value) => f64::from(u8::from(*value)),
+      Value::Bytes(_) | Value::String(_) => return None,
+    })
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Value::U8(value) => write!(f, "{value}"),
+      Value::I8(value) => write!(f, "{value}"),
+      Value::U16(value) => write!(f, "{value}"),
+      Value::I32(value) => write!(f, "{value}"),
+      Value::U32(value) => write!(f, "{value}"),
+      Value::I64(value) => write!(f, "{value}"),
+      Value::U64(value) => write!(f, "{value}"),
+      Value::F32(value) => write!(f, "{value}"),
+      Value::F64(value) => write!(f, "{value}"),
+      Value::Bool(value) => write!(f, "{value}"),
+      Value::Bytes(bytes) => {
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+        write!(f, "{}", hex.join(" "))
+      }
+      Value::String(text) => write!(f, "{text}"),
+    }
+  }
+}
+
+impl ValueType {
+  /// Number of bytes read for this type.
+  pub(crate) fn size(&self) -> usize {
+    match self {
+      ValueType::U8 | ValueType::I8 | ValueType::Bool => 1,
+      ValueType::U16 => 2,
+      ValueType::I32 | ValueType::U32 | ValueType::F32 => 4,
+      ValueType::I64 | ValueType::U64 | ValueType::F64 => 8,
+      ValueType::Bytes { len } | ValueType::String { len } => *len,
+    }
+  }
+
+  pub(crate) fn decode(&self, bytes: &[u8], endian: Endian) -> Result<Value, String> {
+    Ok(match self {
+      ValueType::U8 => Value::U8(decode!(u8, bytes, endian)),
+      ValueType::I8 => Value::I8(decode!(i8, bytes, endian)),
+      ValueType::U16 => Value::U16(decode!(u16, bytes, endian)),
+      ValueType::I32 => Value::I32(decode!(i32, bytes, endian)),
+      ValueType::U32 => Value::U32(decode!(u32, bytes, endian)),
+      ValueType::I64 => Value::I64(decode!(i64, bytes, endian)),
+      ValueType::U64 => Value::U64(decode!(u64, bytes, endian)),
+      ValueType::F32 => Value::F32(decode!(f32, bytes, endian)),
+      ValueType::F64 => Value::F64(decode!(f64, bytes, endian)),
+      ValueType::Bool => Value::Bool(decode!(u8, bytes, endian) != 0),
+      ValueType::Bytes { .. } => Value::Bytes(bytes.to_vec()),
+      ValueType::String { .. } => {
+        let end = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+        Value::String(String::from_utf8_lossy(&bytes[..end]).into_owned())
+      }
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn round_trip(value: Value, value_type: ValueType, endian: Endian) -> Value {
+    let bytes = value.to_bytes(endian);
+    assert_eq!(bytes.len(), value_type.size());
+    value_type.decode(&bytes, endian).unwrap()
+  }
+
+  #[test]
+  fn numbers_round_trip_in_both_byte_orders() {
+    let cases = [
+      (Value::U8(0xAB), ValueType::U8),
+      (Value::I8(-5), ValueType::I8),
+      (Value::U16(0xBEEF), ValueType::U16),
+      (Value::I32(-123_456), ValueType::I32),
+      (Value::U32(0xDEAD_BEEF), ValueType::U32),
+      (Value::I64(-1 << 40), ValueType::I64),
+      (Value::U64(u64::MAX - 1), ValueType::U64),
+      (Value::F32(3.5), ValueType::F32),
+      (Value::F64(-0.125), ValueType::F64),
+      (Value::Bool(true), ValueType::Bool),
+    ];
+    for (value, value_type) in cases {
+      for endian in [Endian::Little, Endian::Big] {
+        let decoded = round_trip(value.clone(), value_type, endian);
+        assert_eq!(format!("{decoded:?}"), format!("{value:?}"), "{endian:?}");
+      }
+    }
+  }
+
+  #[test]
+  fn encodes_with_the_requested_byte_order() {
+    assert_eq!(Value::U32(0x1122_3344).to_bytes(Endian::Little), [0x44, 0x33, 0x22, 0x11]);
+    assert_eq!(Value::U32(0x1122_3344).to_bytes(Endian::Big), [0x11, 0x22, 0x33, 0x44]);
+    assert_eq!(Value::F32(1.0).to_bytes(Endian::Big), [0x3F, 0x80, 0x00, 0x00]);
+    let decoded = ValueType::U16.decode(&[0x12, 0x34], Endian::Big).unwrap();
+    assert_eq!(decoded.as_f64(), Some(f64::from(0x1234u16)));
+    // Single bytes have no order.
+    assert_eq!(Value::I8(-1).to_bytes(Endian::Big), [0xFF]);
+  }
+
+  #[test]
+  fn decodes_bytes_and_nul_terminated_strings() {
+    let bytes = ValueType::Bytes { len: 3 }.decode(&[1, 2, 3], Endian::Little).unwrap();
+    assert_eq!(bytes.to_string(), "01 02 03");
+    let string = ValueType::String { len: 8 }.decode(b"Sky\0junk", Endian::Little).unwrap();
+    assert_eq!(string.to_string(), "Sky");
+    let unterminated = ValueType::String { len: 3 }.decode(b"abc", Endian::Little).unwrap();
+    assert_eq!(unterminated.to_string(), "abc");
+    assert_eq!(Value::String("hi".to_string()).to_bytes(Endian::Big), b"hi");
+  }
+
+  #[test]
+  fn rejects_truncated_reads() {
+    let error = ValueType::U32.decode(&[1, 2], Endian::Little).unwrap_err();
+    assert_eq!(error, "Value read was truncated");
+  }
+
+  #[test]
+  fn caps_buffer_lengths() {
+    let parse = |json: &str| serde_json::from_str::<ValueType>(json);
+    assert_eq!(parse(r#"{ "type": "bytes", "len": 16 }"#).unwrap().size(), 16);
+    let max = format!(r#"{{ "type": "string", "len": {MAX_READ_LEN} }}"#);
+    assert_eq!(parse(&max).unwrap().size(), MAX_READ_LEN);
+    for len in [0, MAX_READ_LEN + 1, usize::MAX] {
+      let json = format!(r#"{{ "type": "bytes", "len": {len} }}"#);
+      assert!(parse(&json).is_err(), "{len}");
+    }
+  }
+}
@@ -0,0 +1,124 @@
+use serde::Serialize;
+use tauri::State;
+
+use super::state::{find_session, ProcessHandle, ProcessState};
+
+const MAX_PAGE_LEN: usize = 0x10000;
+/// Unreadable ranges are found at this granularity.
+const PAGE_SIZE: usize = 0x1000;
+
+/// What is known about a range of a page. Ranges are clipped to the page.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum Annotation {
+  /// Bytes currently patched, with what they held before.
+  #[serde(rename_all = "camelCase")]
+  Patched {
+    start: usize,
+    len: usize,
+    original: Vec<u8>,
+    site: Option<String>,
+    /// Features holding the patch.
+    features: Vec<String>,
+  },
+  Module { start: usize, len: usize, name: String },
+  Unreadable { start: usize, len: usize },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MemoryPage {
+  address: usize,
+  /// `null` for bytes that could not be read.
+  bytes: Vec<Option<u8>>,
+  annotations: Vec<Annotation>,
+}
+
+/// Intersection of `[start, start + len)` with the page, if any.
+fn clip(page: (usize, usize), start: usize, len: usize) -> Option<(usize, usize)> {
+  let from = start.max(page.0);
+  let to = (start + len).min(page.1);
+  (from < to).then_some((from, to - from))
+}
+
+/// Reads what it can, page by page, so one unmapped page does not hide the
+/// rest of the range.
+fn read_bytes(handle: &ProcessHandle, address: usize, len: usize) -> Vec<Option<u8>> {
+  if let Ok(bytes) = handle.backend.read(address, len) {
+    return bytes.into_iter().map(Some).collect();
+  }
+  let mut bytes = Vec::with_capacity(len);
+  let mut cursor = address;
+  while cursor < address + len {
+    let next = ((cursor / PAGE_SIZE) + 1) * PAGE_SIZE;
+    let chunk = next.min(address + len) - cursor;
+    match handle.backend.read(cursor, chunk) {
+      Ok(chunk) => bytes.extend(chunk.into_iter().map(Some)),
+      Err(_) => bytes.extend(std::iter::repeat(None).take(chunk)),
+    }
+    cursor += chunk;
+  }
+  bytes
+}
+
+fn annotate(handle: &ProcessHandle, address: usize, bytes: &[Option<u8>]) -> Vec<Annotation> {
+  let page = (address, address + bytes.len());
+  let mut annotations = Vec::new();
+
+  for (start, record) in &handle.patches {
+    let Some((from, len)) = clip(page, *start, record.original.len()) else {
+      continue;
+    };
+    let site = handle
+      .sites
+      .iter()
+      .find(|(_, resolved)| resolved.as_ref().is_ok_and(|site| site == start))
+      .map(|(name, _)| name.clone());
+    annotations.push(Annotation::Patched {
+      start: from,
+      len,
+      original: record.original[from - start..from - start + len].to_vec(),
+      site,
+      features: record.owners.iter().cloned().collect(),
+    });
+  }
+
+  let mut modules = handle.backend.modules();
+  modules.sort_by_key(|module| module.base);
+  annotations.extend(modules.into_iter().filter_map(|module| {
+    let (start, len) = clip(page, module.base, module.size)?;
+    Some(Annotation::Module { start, len, name: module.name })
+  }));
+
+  let mut index = 0;
+  while index < bytes.len() {
+    if bytes[index].is_some() {
+      index += 1;
+      continue;
+    }
+    let gap = bytes[index..].iter().take_while(|byte| byte.is_none()).count();
+    annotations.push(Annotation::Unreadable { start: address + index, len: gap });
+    index += gap;
+  }
+  annotations
+}
+
+/// Up to 64 KiB at `address` with an overlay of patched bytes, module ranges
+/// and unreadable gaps, for the hex viewer.
+#[tauri::command]
+pub(crate) fn read_page(
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  address: usize,
+  len: usize,
+) -> Result<MemoryPage, String> {
+  if len > MAX_PAGE_LEN {
+    return Err(format!("Pages are at most {MAX_PAGE_LEN:#x} bytes"));
+  }
+  address.checked_add(len).ok_or_else(|| "Address range overflows".to_string())?;
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  let bytes = read_bytes(handle, address, len);
+  let annotations = annotate(handle, address, &bytes);
+  Ok(MemoryPage { address, bytes, annotations })
+}
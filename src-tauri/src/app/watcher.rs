@@ -0,0 +1,147 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::backend;
+use super::commands;
+use super::features;
+use super::settings;
+use super::state::{AttachResponse, ProcessState};
+
+const AUTO_ATTACH_POLL: Duration = Duration::from_secs(2);
+/// How long Sky.exe must have been running before auto-attach touches it, so
+/// the module is mapped and the game has finished starting up.
+const AUTO_ATTACH_SETTLE: Duration = Duration::from_secs(5);
+
+/// Game the user detached from by hand; auto-attach leaves it alone.
+static DETACHED_PID: Mutex<Option<u32>> = Mutex::new(None);
+/// Pids with an exit watcher running, so attaching again does not start a
+/// second one.
+static WATCHED: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ProcessExited {
+  pid: u32,
+  exit_code: Option<i32>,
+}
+
+/// Waits for an attached client to exit in the background, unless a watcher
+/// for `pid` is already running. When it exits, its stale session and
+/// recorded originals are dropped (there is nothing left to restore) and
+/// `process-exited` is emitted so the UI can update.
+pub(crate) fn spawn_exit_watcher(app: AppHandle, pid: u32) {
+  if WATCHED.lock().is_ok_and(|mut watched| !watched.insert(pid)) {
+    return;
+  }
+  thread::spawn(move || {
+    let exit_code = backend::wait_for_exit(pid);
+    if let Ok(mut watched) = WATCHED.lock() {
+      watched.remove(&pid);
+    }
+    let state = app.state::<ProcessState>();
+    let Ok(mut guard) = state.sessions.lock() else {
+      return;
+    };
+    let Some(mut handle) = guard.remove(&pid) else {
+      return;
+    };
+    handle.patches.clear();
+    drop(guard);
+    let _ = app.emit("process-exited", ProcessExited { pid, exit_code });
+  });
+}
+
+pub(crate) fn suppress_auto_attach(pid: u32) {
+  if let Ok(mut detached) = DETACHED_PID.lock() {
+    *detached = Some(pid);
+  }
+}
+
+/// A remembered feature that could not be applied again.
+#[derive(Serialize, Clone)]
+struct ReapplyFailure {
+  id: String,
+  error: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AutoAttached {
+  #[serde(flatten)]
+  attach: AttachResponse,
+  /// Remembered features that were applied again.
+  applied: Vec<String>,
+  failed: Vec<ReapplyFailure>,
+}
+
+/// Polls for the game while `autoAttach` is enabled and no session is open.
+/// Once Sky.exe has been running for a moment it is attached, the features
+/// active last session are applied again and `auto-attached` is emitted.
+pub(crate) fn spawn_auto_attach(app: AppHandle) {
+  thread::spawn(move || {
+    let mut seen: Option<(u32, Instant)> = None;
+    loop {
+      thread::sleep(AUTO_ATTACH_POLL);
+      let settings = settings::current_settings(&app);
+      let state = app.state::<ProcessState>();
+      let attached = state.sessions.lock().map_or(true, |guard| !guard.is_empty());
+      if !settings.auto_attach || attached {
+        seen = None;
+        continue;
+      }
+      let Some(pid) = backend::find_game_pid() else {
+        seen = None;
+        continue;
+      };
+      if DETACHED_PID.lock().is_ok_and(|detached| *detached == Some(pid)) {
+        continue;
+      }
+      match seen {
+        Some((seen_pid, since)) if seen_pid == pid => {
+          if since.elapsed() < AUTO_ATTACH_SETTLE {
+            continue;
+          }
+        }
+        _ => {
+          seen = Some((pid, Instant::now()));
+          continue;
+        }
+      }
+      let Ok(attach) = commands::attach(&app, &state, pid) else {
+        continue;
+      };
+      let (applied, failed) = reapply(&state, pid, &settings.active_features);
+      let _ = app.emit("auto-attached", AutoAttached { attach, applied, failed });
+    }
+  });
+}
+
+fn reapply(
+  state: &ProcessState,
+  pid: u32,
+  active: &BTreeMap<String, HashMap<String, f32>>,
+) -> (Vec<String>, Vec<ReapplyFailure>) {
+  let mut applied = Vec::new();
+  let mut failed = Vec::new();
+  let Ok(mut guard) = state.sessions.lock() else {
+    return (applied, failed);
+  };
+  let Some(handle) = guard.get_mut(&pid) else {
+    return (applied, failed);
+  };
+  for (id, params) in active {
+    match features::set_feature(handle, id, true, params) {
+      Ok(()) => applied.push(id.clone()),
+      Err(error) => failed.push(ReapplyFailure {
+        id: id.clone(),
+        error: error.message,
+      }),
+    }
+  }
+  (applied, failed)
+}
@@ -0,0 +1,145 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use super::freeze;
+use super::state::{find_session, ProcessHandle, ProcessState};
+use super::value::{Probe, Value, ValueType};
+
+/// Profile values a waypoint is built from; see `OffsetProfile::values`.
+const POSITION_VALUES: [&str; 3] = ["positionX", "positionY", "positionZ"];
+const AREA_VALUE: &str = "area";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Waypoint {
+  pub(crate) name: String,
+  /// Level/area the position belongs to, as read from the `area` value.
+  pub(crate) area: String,
+  pub(crate) position: [f32; 3],
+}
+
+fn waypoints_path(app: &AppHandle) -> Result<PathBuf, String> {
+  let mut path = app
+    .path()
+    .app_config_dir()
+    .map_err(|_| "Failed to resolve app config directory".to_string())?;
+  path.push("waypoints.json");
+  Ok(path)
+}
+
+fn load(app: &AppHandle) -> Result<Vec<Waypoint>, String> {
+  let path = waypoints_path(app)?;
+  if !path.exists() {
+    return Ok(Vec::new());
+  }
+  let contents =
+    fs::read_to_string(&path).map_err(|_| "Failed to read waypoints file".to_string())?;
+  serde_json::from_str(&contents).map_err(|_| "Failed to parse waypoints file".to_string())
+}
+
+fn store(app: &AppHandle, waypoints: &[Waypoint]) -> Result<(), String> {
+  let path = waypoints_path(app)?;
+  if let Some(parent) = path.parent() {
+    let _ = fs::create_dir_all(parent);
+  }
+  let payload =
+    serde_json::to_string_pretty(waypoints).map_err(|_| "Failed to encode waypoints".to_string())?;
+  fs::write(path, payload).map_err(|_| "Failed to write waypoints file".to_string())
+}
+
+fn probe<'a>(handle: &'a ProcessHandle, name: &str) -> Result<&'a Probe, String> {
+  handle
+    .profile
+    .values
+    .get(name)
+    .ok_or_else(|| format!("The {} profile does not declare `{name}`", handle.profile.name))
+}
+
+fn current_area(handle: &ProcessHandle) -> Result<String, String> {
+  Ok(probe(handle, AREA_VALUE)?.read(handle)?.to_string())
+}
+
+fn current_position(handle: &ProcessHandle) -> Result<[f32; 3], String> {
+  let mut position = [0.0; 3];
+  for (axis, name) in position.iter_mut().zip(POSITION_VALUES) {
+    *axis = match probe(handle, name)?.read(handle)? {
+      Value::F32(value) => value,
+      Value::F64(value) => value as f32,
+      _ => return Err(format!("`{name}` must be declared as f32 or f64")),
+    };
+  }
+  Ok(position)
+}
+
+#[tauri::command]
+pub(crate) fn list_waypoints(app: AppHandle) -> Result<Vec<Waypoint>, String> {
+  load(&app)
+}
+
+/// Saves the player's current position under `name`, replacing any waypoint
+/// with the same name.
+#[tauri::command]
+pub(crate) fn save_waypoint(
+  app: AppHandle,
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  name: String,
+) -> Result<Waypoint, String> {
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  let waypoint = Waypoint {
+    name,
+    area: current_area(handle)?,
+    position: current_position(handle)?,
+  };
+  drop(guard);
+  let mut waypoints = load(&app)?;
+  waypoints.retain(|existing| existing.name != waypoint.name);
+  waypoints.push(waypoint.clone());
+  store(&app, &waypoints)?;
+  Ok(waypoint)
+}
+
+/// Moves the player to a saved waypoint. Only allowed in the area it was
+/// saved in, since coordinates mean nothing in another level.
+#[tauri::command]
+pub(crate) fn restore_waypoint(
+  app: AppHandle,
+  state: State<'_, ProcessState>,
+  session: Option<u32>,
+  name: String,
+) -> Result<(), String> {
+  let waypoint = load(&app)?
+    .into_iter()
+    .find(|waypoint| waypoint.name == name)
+    .ok_or_else(|| format!("No waypoint named {name}"))?;
+  let guard = state.sessions.lock().map_err(|_| "State lock error".to_string())?;
+  let handle = find_session(&guard, session)?;
+  let area = current_area(handle)?;
+  if area != waypoint.area {
+    return Err(format!("{name} was saved in {}, not {area}", waypoint.area));
+  }
+  for (axis, value_name) in waypoint.position.iter().zip(POSITION_VALUES) {
+    let probe = probe(handle, value_name)?;
+    let value = match probe.value_type {
+      ValueType::F64 => Value::F64(f64::from(*axis)),
+      _ => Value::F32(*axis),
+    };
+    freeze::write(handle, &probe.target, &value, probe.endian)?;
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn delete_waypoint(app: AppHandle, name: String) -> Result<(), String> {
+  let mut waypoints = load(&app)?;
+  let before = waypoints.len();
+  waypoints.retain(|waypoint| waypoint.name != name);
+  if waypoints.len() == before {
+    return Err(format!("No waypoint named {name}"));
+  }
+  store(&app, &waypoints)
+}
@@ -0,0 +1,9 @@
+/// X11 and Wayland expose no portable way to read another client's window
+/// class, so hotkey focus gating is unavailable here.
+pub(crate) fn foreground_window_class() -> Result<String, String> {
+  Err("Foreground window lookup is not supported on Linux".to_string())
+}
+
+/// Mouse button hotkeys rely on a Win32 low-level hook; keyboard shortcuts
+/// still work through the global-shortcut plugin.
+pub(crate) fn spawn_mouse_hook() {}
@@ -0,0 +1,9 @@
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(target_os = "linux")]
+pub(crate) use linux::{foreground_window_class, spawn_mouse_hook};
+#[cfg(windows)]
+pub(crate) use windows::{foreground_window_class, spawn_mouse_hook};
@@ -5,21 +5,21 @@ use windows_sys::Win32::UI::WindowsAndMessaging::{
   UnhookWindowsHookEx, MSLLHOOKSTRUCT, MSG, WH_MOUSE_LL, WM_MBUTTONDOWN, WM_XBUTTONDOWN,
 };
 
-use super::process::read_wide;
-use super::APP_HANDLE;
+use crate::app::backend::windows::read_wide;
+use crate::app::APP_HANDLE;
 
-pub(crate) fn foreground_window_class() -> Option<String> {
+pub(crate) fn foreground_window_class() -> Result<String, String> {
   unsafe {
     let hwnd = GetForegroundWindow();
     if hwnd == 0 {
-      return None;
+      return Err("No foreground window".to_string());
     }
     let mut buffer = [0u16; 256];
     let len = GetClassNameW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
     if len <= 0 {
-      return None;
+      return Err("No foreground window".to_string());
     }
-    Some(read_wide(&buffer))
+    Ok(read_wide(&buffer))
   }
 }
 
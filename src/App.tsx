@@ -10,18 +10,20 @@ import './App.css'
 type AttachResponse = {
   pid: number
   base: number
+  profile: string
+  unresolved: { name: string; error: string }[]
 }
 
-type FeatureOperation =
-  | { type: 'patch'; offset: number; bytes: number[] }
-  | { type: 'nop'; offset: number; size: number }
-  | { type: 'float'; offset: number; value: number }
+type FeatureCategory = 'player' | 'movement' | 'camera' | 'settings'
 
 type FeatureToggle = {
   id: string
   label: string
-  desc: string
-  ops: FeatureOperation[]
+  description: string
+  category: FeatureCategory
+  params: { id: string; label: string; min: number; max: number; default: number }[]
+  available: boolean
+  unavailableReason: string | null
 }
 
 type CategoryKey =
@@ -37,6 +39,44 @@ type AppSettings = {
   nonActivateWindow: boolean
   appScale: number
   featureHotkeys: Record<string, string>
+  keepPatchesOnDetach: boolean
+  autoAttach: boolean
+}
+
+type GameProcess = {
+  pid: number
+  path: string | null
+  windowTitle: string | null
+  startTime: number | null
+  moduleBase: number | null
+}
+
+type SessionInfo = {
+  pid: number
+  profile: string
+  active: string[]
+}
+
+type StatusResponse = {
+  attached: boolean
+  pid: number | null
+  sessions: SessionInfo[]
+}
+
+type AutoAttached = AttachResponse & {
+  applied: string[]
+  failed: { id: string; error: string }[]
+}
+
+type ProcessExited = {
+  pid: number
+  exitCode: number | null
+}
+
+type DetachResponse = {
+  restored: number
+  kept: number
+  failed: { offset: number; error: string }[]
 }
 
 type ForegroundWindow = {
@@ -44,23 +84,25 @@ type ForegroundWindow = {
 }
 
 type OffsetsResponse = {
-  invincibility: number
-  runSpeed: number
+  profile: string
+  build: { timestamp: number; imageSize: number } | null
+  invincibility: number | null
+  runSpeed: number | null
   defaultRunSpeed: number
-  infiniteEnergy: number
-  infiniteBreath: number
-  antiRainDrain: number
-  antiAfk: number
-  superJump: number
-  superSwim: number
-  superFlight: number
-  antiSink: number
-  disableCamSnap: number
-  freeZoom: number
-  disableCamRotation: number
-  firstPerson: number
-  showCursor: number
-  superRunPatch: number
+  infiniteEnergy: number | null
+  infiniteBreath: number | null
+  antiRainDrain: number | null
+  antiAfk: number | null
+  superJump: number | null
+  superSwim: number | null
+  superFlight: number | null
+  antiSink: number | null
+  disableCamSnap: number | null
+  freeZoom: number | null
+  disableCamRotation: number | null
+  firstPerson: number | null
+  showCursor: number | null
+  superRunPatch: number | null
 }
 
 const categories: { id: CategoryKey; label: string; icon: ReactElement }[] = [
@@ -143,131 +185,19 @@ const themeOptions: Array<{
   },
 ]
 
-const buildToggleSets = (offsets: OffsetsResponse | null) => {
-  if (!offsets) {
-    return {
-      playerToggles: [] as FeatureToggle[],
-      movementToggles: [] as FeatureToggle[],
-      cameraToggles: [] as FeatureToggle[],
-      settingsToggles: [] as FeatureToggle[],
-    }
-  }
+const SUPER_RUN_ID = 'super-run'
 
-  const playerToggles: FeatureToggle[] = [
-    {
-      id: 'godmode',
-      label: 'Godmode',
-      desc: 'Enables invincibility against all damage sources',
-      ops: [{ type: 'patch', offset: offsets.invincibility, bytes: [0x01] }],
-    },
-    {
-      id: 'infinite-energy',
-      label: 'Infinite Energy',
-      desc: 'Never run out of wing energy',
-      ops: [{ type: 'patch', offset: offsets.infiniteEnergy, bytes: [0x01] }],
-    },
-    {
-      id: 'infinite-breath',
-      label: 'Infinite Breath',
-      desc: 'Never run out of breath underwater',
-      ops: [{ type: 'nop', offset: offsets.infiniteBreath, size: 6 }],
-    },
-    {
-      id: 'anti-rain',
-      label: 'Anti Rain Drain',
-      desc: 'Prevents rain from draining your light',
-      ops: [{ type: 'patch', offset: offsets.antiRainDrain, bytes: [0x01] }],
-    },
-    {
-      id: 'anti-afk',
-      label: 'Anti AFK',
-      desc: 'Prevents entering AFK state when idle',
-      ops: [{ type: 'patch', offset: offsets.antiAfk, bytes: [0x00] }],
-    },
-  ]
-
-  const movementToggles: FeatureToggle[] = [
-    {
-      id: 'super-jump',
-      label: 'Super Jump',
-      desc: 'Jump further',
-      ops: [
-        {
-          type: 'patch',
-          offset: offsets.superJump,
-          bytes: [0x00, 0x00, 0x20, 0x41, 0x9a],
-        },
-      ],
-    },
-    {
-      id: 'super-swim',
-      label: 'Super Swim',
-      desc: 'Swim faster',
-      ops: [
-        {
-          type: 'patch',
-          offset: offsets.superSwim,
-          bytes: [0x00, 0x00, 0x48, 0x42, 0x6f],
-        },
-      ],
-    },
-    {
-      id: 'super-flight',
-      label: 'Super Flight',
-      desc: 'Fly faster',
-      ops: [
-        {
-          type: 'patch',
-          offset: offsets.superFlight,
-          bytes: [0xc7, 0x01, 0x00, 0x00, 0xc8, 0x42],
-        },
-      ],
-    },
-    {
-      id: 'anti-sink',
-      label: 'Anti Sink',
-      desc: 'Prevents sinking in water',
-      ops: [{ type: 'float', offset: offsets.antiSink, value: 100.0 }],
-    },
-  ]
-
-  const cameraToggles: FeatureToggle[] = [
-    {
-      id: 'disable-cam-snap',
-      label: 'Disable Camera Snapping',
-      desc: 'Prevents camera from automatically snapping',
-      ops: [{ type: 'patch', offset: offsets.disableCamSnap, bytes: [0x00] }],
-    },
-    {
-      id: 'free-zoom',
-      label: 'Disable Zoom Restrictions',
-      desc: 'Removes limits on camera zoom',
-      ops: [{ type: 'nop', offset: offsets.freeZoom, size: 9 }],
-    },
-    {
-      id: 'disable-cam-rotation',
-      label: 'Disable Camera Rotation',
-      desc: 'Prevents camera from rotating',
-      ops: [{ type: 'nop', offset: offsets.disableCamRotation, size: 2 }],
-    },
-    {
-      id: 'first-person',
-      label: 'First Person',
-      desc: 'Enables first-person camera mode',
-      ops: [{ type: 'nop', offset: offsets.firstPerson, size: 5 }],
-    },
-  ]
-
-  const settingsToggles: FeatureToggle[] = [
-    {
-      id: 'show-cursor',
-      label: 'Show Cursor',
-      desc: 'Keeps the system cursor visible while in-game.',
-      ops: [{ type: 'patch', offset: offsets.showCursor, bytes: [0x01] }],
-    },
-  ]
-
-  return { playerToggles, movementToggles, cameraToggles, settingsToggles }
+const buildToggleSets = (features: FeatureToggle[]) => {
+  const inCategory = (category: FeatureCategory) =>
+    features.filter((feature) => feature.category === category)
+  return {
+    playerToggles: inCategory('player'),
+    movementToggles: inCategory('movement').filter(
+      (feature) => feature.id !== SUPER_RUN_ID
+    ),
+    cameraToggles: inCategory('camera'),
+    settingsToggles: inCategory('settings'),
+  }
 }
 
 const DEFAULT_WINDOW_SIZE = { width: 1000, height: 760 }
@@ -284,6 +214,8 @@ function App() {
   const [theme, setTheme] = useState<ThemeKey>('aqua')
   const [alwaysOnTop, setAlwaysOnTop] = useState(false)
   const [reduceMotion, setReduceMotion] = useState(false)
+  const [keepPatchesOnDetach, setKeepPatchesOnDetach] = useState(false)
+  const [autoAttach, setAutoAttach] = useState(false)
   const [nonActivateWindow, setNonActivateWindow] = useState(false)
   const [appScale, setAppScale] = useState(1)
   const [featureHotkeys, setFeatureHotkeys] = useState<Record<string, string>>(
@@ -312,11 +244,15 @@ function App() {
   const hotkeyLastTriggerRef = useRef<Record<string, number>>({})
   const [attached, setAttached] = useState(false)
   const [pid, setPid] = useState<number | null>(null)
+  const pidRef = useRef<number | null>(null)
+  const [sessions, setSessions] = useState<SessionInfo[]>([])
+  const [candidates, setCandidates] = useState<GameProcess[]>([])
   const [superRunEnabled, setSuperRunEnabled] = useState(false)
   const [pendingSuperRunSpeed, setPendingSuperRunSpeed] = useState(20)
   const [toasts, setToasts] = useState<{ id: number; message: string; variant: 'error' | 'success' }[]>([])
 
   const [offsets, setOffsets] = useState<OffsetsResponse | null>(null)
+  const [features, setFeatures] = useState<FeatureToggle[]>([])
 
   const [categoryDirection, setCategoryDirection] = useState<'left' | 'right'>(
     'right'
@@ -346,28 +282,27 @@ function App() {
     }, 4000)
   }
 
-  useEffect(() => {
-    let active = true
-    const loadOffsets = async () => {
-      if (!(await isTauri())) return
-      try {
-        const result = await invoke<OffsetsResponse>('get_offsets')
-        if (!active) return
-        setOffsets(result)
-      } catch {
-        if (!active) return
-        setOffsets(null)
-      }
+  const refreshOffsets = async (session: number | null = pidRef.current) => {
+    if (!(await isTauri())) return
+    try {
+      setOffsets(await invoke<OffsetsResponse>('get_offsets', { session }))
+    } catch {
+      setOffsets(null)
     }
-    loadOffsets()
-    return () => {
-      active = false
+    try {
+      setFeatures(await invoke<FeatureToggle[]>('list_features', { session }))
+    } catch {
+      setFeatures([])
     }
+  }
+
+  useEffect(() => {
+    void refreshOffsets()
   }, [])
 
   const { playerToggles, movementToggles, cameraToggles, settingsToggles } = useMemo(
-    () => buildToggleSets(offsets),
-    [offsets]
+    () => buildToggleSets(features),
+    [features]
   )
 
   const handleWindowAction = async (action: 'minimize' | 'close') => {
@@ -476,46 +411,23 @@ function App() {
     await appWindow.startDragging()
   }
 
-  const applyOperation = async (operation: FeatureOperation, enabled: boolean) => {
-    if (!attached) return
-    if (operation.type === 'patch') {
-      await invoke('apply_patch', {
-        offset: operation.offset,
-        bytes: operation.bytes,
-        enabled,
-      })
-      return
-    }
-    if (operation.type === 'nop') {
-      await invoke('apply_nop', {
-        offset: operation.offset,
-        size: operation.size,
-        enabled,
-      })
-      return
-    }
-    await invoke('apply_float', {
-      offset: operation.offset,
-      value: operation.value,
-      enabled,
-    })
-  }
-
   const handleToggleState = async (feature: FeatureToggle, next: boolean) => {
     if (!attached) return
     const current = Boolean(activeTogglesRef.current[feature.id])
     if (current === next) return
     setActiveToggles((current) => ({ ...current, [feature.id]: next }))
     try {
-      for (const operation of feature.ops) {
-        await applyOperation(operation, next)
+      if (!feature.available) {
+        throw new Error(
+          feature.unavailableReason ??
+            'This feature is unavailable for the running game build.'
+        )
       }
+      await invoke('set_feature', { session: pidRef.current, id: feature.id, enabled: next })
     } catch (err) {
       setActiveToggles((current) => ({ ...current, [feature.id]: !next }))
       addToast(
-        err instanceof Error
-          ? err.message
-          : 'Failed to apply feature. Reattach and try again.',
+        formatError(err, 'Failed to apply feature. Reattach and try again.'),
         'error'
       )
     }
@@ -567,6 +479,10 @@ function App() {
     activeTogglesRef.current = activeToggles
   }, [activeToggles])
 
+  useEffect(() => {
+    pidRef.current = pid
+  }, [pid])
+
   useEffect(() => {
     if (!listeningHotkey) return
     setHotkeyCaptureActive(true)
@@ -725,6 +641,50 @@ function App() {
     }
   }, [attached])
 
+  useEffect(() => {
+    let unlistenPromise: Promise<() => void> | null = null
+    let active = true
+    const setup = async () => {
+      if (!(await isTauri())) return
+      if (!active) return
+      const unlistenAttached = listen<AutoAttached>('auto-attached', (event) => {
+        const response = event.payload
+        setAttached(true)
+        selectSession({
+          pid: response.pid,
+          profile: response.profile,
+          active: response.applied,
+        })
+        void syncSessions()
+        addToast(`Auto-attached to Sky.exe (${response.profile} offsets)`, 'success')
+        if (response.failed.length > 0) {
+          const names = response.failed.map((feature) => feature.id).join(', ')
+          addToast(`Could not re-apply: ${names}`, 'error')
+        }
+      })
+      const unlistenExited = listen<ProcessExited>('process-exited', (event) => {
+        void syncSessions(event.payload.pid)
+        const { pid: exited, exitCode: code } = event.payload
+        addToast(
+          code === null
+            ? `Sky.exe (PID ${exited}) has exited`
+            : `Sky.exe (PID ${exited}) has exited (code ${code})`,
+          'error'
+        )
+      })
+      unlistenPromise = Promise.all([unlistenAttached, unlistenExited]).then(
+        (stops) => () => stops.forEach((stop) => stop())
+      )
+    }
+    setup()
+    return () => {
+      active = false
+      if (unlistenPromise) {
+        void unlistenPromise.then((stop) => stop())
+      }
+    }
+  }, [])
+
   useEffect(() => {
     let unlistenPromise: Promise<() => void> | null = null
     let active = true
@@ -814,7 +774,7 @@ function App() {
     >
       <div>
         <h3>{feature.label}</h3>
-        <p>{feature.desc}</p>
+        <p>{feature.description}</p>
       </div>
       <div className="wm-card__controls">
         <div
@@ -901,22 +861,17 @@ function App() {
 
   const handleSuperRunApply = async () => {
     if (!attached) return
-    if (!offsets) return
     try {
-      const value = pendingSuperRunSpeed
-      if (!superRunEnabled) {
-        setSuperRunEnabled(true)
-        await applyOperation(
-          { type: 'patch', offset: offsets.superRunPatch, bytes: [0x00] },
-          true
-        )
-      }
-      await invoke('set_run_speed', { value })
+      await invoke('set_feature', {
+        id: SUPER_RUN_ID,
+        enabled: true,
+        session: pidRef.current,
+        params: { speed: pendingSuperRunSpeed },
+      })
+      setSuperRunEnabled(true)
     } catch (err) {
       addToast(
-        err instanceof Error
-          ? err.message
-          : 'Failed to apply Super Run speed.',
+        formatError(err, 'Failed to apply Super Run speed.'),
         'error'
       )
     }
@@ -926,38 +881,96 @@ function App() {
     if (!attached) return
     if (!offsets) return
     try {
+      await invoke('set_feature', { session: pidRef.current, id: SUPER_RUN_ID, enabled: false })
       setSuperRunEnabled(false)
-      await applyOperation(
-        { type: 'patch', offset: offsets.superRunPatch, bytes: [0x00] },
-        false
-      )
-      const value = offsets.defaultRunSpeed
-      setPendingSuperRunSpeed(value)
-      await invoke('reset_run_speed')
+      setPendingSuperRunSpeed(offsets.defaultRunSpeed)
     } catch (err) {
-      addToast(
-        err instanceof Error ? err.message : 'Failed to reset Super Run.',
-        'error'
+      addToast(formatError(err, 'Failed to reset Super Run.'), 'error')
+    }
+  }
+
+  /** Shows one session's toggles and offsets; `null` shows the detached state. */
+  const selectSession = (session: SessionInfo | null) => {
+    pidRef.current = session?.pid ?? null
+    setPid(session?.pid ?? null)
+    const active = session?.active ?? []
+    setActiveToggles(
+      Object.fromEntries(
+        active.filter((id) => id !== SUPER_RUN_ID).map((id) => [id, true])
       )
+    )
+    setSuperRunEnabled(active.includes(SUPER_RUN_ID))
+    void refreshOffsets(session?.pid ?? null)
+  }
+
+  /**
+   * Reloads the open sessions. The selected one stays selected unless it is
+   * `gone`, in which case the first remaining session is shown instead.
+   */
+  const syncSessions = async (gone: number | null = null) => {
+    const status = await invoke<StatusResponse>('status')
+    setSessions(status.sessions)
+    setAttached(status.attached)
+    const current = pidRef.current
+    if (current !== gone && status.sessions.some((session) => session.pid === current)) {
+      return
     }
+    selectSession(status.sessions[0] ?? null)
   }
 
   const handleAttach = async () => {
     try {
-      const response = await invoke<AttachResponse>('attach_process')
-      setAttached(true)
-      setPid(response.pid)
-      addToast('Attached to Sky.exe', 'success')
+      const processes = await invoke<GameProcess[]>('list_game_processes')
+      const fresh = processes.filter(
+        (candidate) => !sessions.some((session) => session.pid === candidate.pid)
+      )
+      if (fresh.length > 1) {
+        setCandidates(fresh)
+        return
+      }
+      if (fresh.length === 1) {
+        await attachTo(fresh[0].pid)
+        return
+      }
     } catch {
-      addToast('Sky.exe not found. Launch the game and try again.', 'error')
+      // Fall through and let attach_process pick the first Sky.exe.
+    }
+    await attachTo(null)
+  }
+
+  const attachTo = async (target: number | null) => {
+    setCandidates([])
+    try {
+      const response = await invoke<AttachResponse>('attach_process', { pid: target })
+      setAttached(true)
+      selectSession({ pid: response.pid, profile: response.profile, active: [] })
+      await syncSessions()
+      addToast(`Attached to Sky.exe (${response.profile} offsets)`, 'success')
+      if (response.unresolved.length > 0) {
+        const names = response.unresolved.map((site) => site.name).join(', ')
+        addToast(`Unavailable on this game build: ${names}`, 'error')
+      }
+    } catch (err) {
+      const message = formatError(err, 'Sky.exe not found')
+      addToast(
+        message === 'Sky.exe not found'
+          ? 'Sky.exe not found. Launch the game and try again.'
+          : `Could not attach to Sky.exe: ${message}`,
+        'error'
+      )
     }
   }
 
   const handleDetach = async () => {
-    await invoke('detach_process')
-    setAttached(false)
-    setPid(null)
-    addToast('Detached from Sky.exe', 'success')
+    const response = await invoke<DetachResponse>('detach_process', { session: pid })
+    await syncSessions(pid)
+    addToast(`Detached from Sky.exe (PID ${pid ?? '-'})`, 'success')
+    if (response.failed.length > 0) {
+      addToast(
+        `Could not restore ${response.failed.length} patched site(s); restart the game to clear them.`,
+        'error'
+      )
+    }
   }
 
   const handleLaunchGame = async () => {
@@ -971,7 +984,7 @@ function App() {
 
   const handleCloseGame = async () => {
     try {
-      await invoke('close_game')
+      await invoke('close_game', { session: pid })
       addToast('Closed Sky.exe', 'success')
     } catch (err) {
       addToast(formatError(err, 'Failed to close Sky.exe.'), 'error')
@@ -1069,7 +1082,7 @@ function App() {
     queryValue.length === 0
       ? list
       : list.filter(
-          (item) => matchesQuery(item.label) || matchesQuery(item.desc)
+          (item) => matchesQuery(item.label) || matchesQuery(item.description)
         )
 
   const filteredPlayer = useMemo(
@@ -1122,6 +1135,8 @@ function App() {
           setNonActivateWindow(Boolean(settings.nonActivateWindow))
           setAppScale(clampAppScale(Number(settings.appScale)))
           setFeatureHotkeys(settings.featureHotkeys ?? {})
+          setKeepPatchesOnDetach(Boolean(settings.keepPatchesOnDetach))
+          setAutoAttach(Boolean(settings.autoAttach))
         } catch {
           if (!active) return
           setTheme('aqua')
@@ -1130,6 +1145,8 @@ function App() {
           setNonActivateWindow(false)
           setAppScale(1)
           setFeatureHotkeys({})
+          setKeepPatchesOnDetach(false)
+          setAutoAttach(false)
         }
         if (active) setSettingsLoaded(true)
         return
@@ -1288,6 +1305,8 @@ function App() {
         nonActivateWindow,
         appScale,
         featureHotkeys,
+        keepPatchesOnDetach,
+        autoAttach,
       }
       if (await isTauri()) {
         await invoke('save_settings', { settings }).catch(() => {})
@@ -1313,6 +1332,8 @@ function App() {
     nonActivateWindow,
     appScale,
     featureHotkeys,
+    keepPatchesOnDetach,
+    autoAttach,
     settingsLoaded,
     hotkeyCaptureActive,
   ])
@@ -1419,7 +1440,6 @@ function App() {
                     <button
                       className="btn btn--primary"
                       onClick={handleAttach}
-                      disabled={attached}
                     >
                       Attach
                     </button>
@@ -1437,12 +1457,41 @@ function App() {
                       Close Game
                     </button>
                   </div>
+                  {candidates.length > 0 ? (
+                    <div className="wm-hero__actions wm-hero__actions--grid">
+                      {candidates.map((candidate) => (
+                        <button
+                          key={candidate.pid}
+                          className="btn btn--ghost"
+                          title={candidate.path ?? undefined}
+                          onClick={() => attachTo(candidate.pid)}
+                        >
+                          PID {candidate.pid}
+                          {candidate.windowTitle ? ` · ${candidate.windowTitle}` : ''}
+                          {candidate.startTime
+                            ? ` · ${new Date(candidate.startTime * 1000).toLocaleTimeString()}`
+                            : ''}
+                        </button>
+                      ))}
+                    </div>
+                  ) : null}
                 </div>
                 <div className="wm-hero__card">
                   <div>
                     <span>Process</span>
                     <strong>{attached ? 'Attached' : 'Waiting'}</strong>
                     <small>{attached ? `PID ${pid ?? '-'}` : 'Sky.exe'}</small>
+                    {sessions.length > 1
+                      ? sessions.map((session) => (
+                          <button
+                            key={session.pid}
+                            className={`btn ${session.pid === pid ? 'btn--primary' : 'btn--ghost'}`}
+                            onClick={() => selectSession(session)}
+                          >
+                            PID {session.pid}
+                          </button>
+                        ))
+                      : null}
                   </div>
                   <div>
                     <span>Active</span>
@@ -1838,6 +1887,18 @@ function App() {
                             nonActivateWindow,
                             setNonActivateWindow
                           )}
+                          {renderSettingsToggle(
+                            'Auto Attach',
+                            'Attach when Sky starts and re-apply last session\'s features.',
+                            autoAttach,
+                            setAutoAttach
+                          )}
+                          {renderSettingsToggle(
+                            'Keep Patches On Detach',
+                            'Leave features applied when detaching or closing the tool.',
+                            keepPatchesOnDetach,
+                            setKeepPatchesOnDetach
+                          )}
                         </div>
                       </section>
                     )}